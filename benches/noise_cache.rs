@@ -0,0 +1,79 @@
+// benches/noise_cache.rs
+// Compara el costo por frame de construir los generadores de ruido en cada fragmento (como lo
+// hacían los shaders antes) contra reutilizar los generadores que guarda el material rocoso.
+// Ejecutar con: cargo bench --bench noise_cache
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+
+#[path = "../src/noise_cache.rs"]
+#[allow(dead_code)]
+mod noise_cache;
+
+use noise_cache::RockyNoise;
+
+// Un frame a pantalla completa de la ventana de 1000x1000
+const FRAGMENTS_PER_FRAME: usize = 1000 * 1000;
+const FRAMES: u32 = 5;
+
+// Posición de muestreo del fragmento `i`, repartida sobre una esfera de radio 100
+fn sample_position(i: usize) -> (f32, f32, f32) {
+    let theta = i as f32 * 0.0137;
+    let phi = i as f32 * 0.0071;
+    (
+        100.0 * theta.sin() * phi.cos(),
+        100.0 * theta.cos(),
+        100.0 * theta.sin() * phi.sin(),
+    )
+}
+
+// Antes: cada fragmento del planeta rocoso configuraba sus dos generadores desde cero
+fn frame_per_fragment() -> f32 {
+    let mut total = 0.0;
+    for i in 0..FRAGMENTS_PER_FRAME {
+        let mut dune_noise = FastNoiseLite::new();
+        dune_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
+        dune_noise.set_frequency(Some(0.02));
+
+        let mut rock_noise = FastNoiseLite::new();
+        rock_noise.set_noise_type(Some(NoiseType::Cellular));
+        rock_noise.set_frequency(Some(0.1));
+
+        let (x, y, z) = sample_position(i);
+        total += dune_noise.get_noise_3d(x, y, z) + rock_noise.get_noise_3d(x, y, z);
+    }
+    total
+}
+
+// Después: los generadores se crean una vez y los fragmentos solo los muestrean
+fn frame_cached(noises: &RockyNoise) -> f32 {
+    let mut total = 0.0;
+    for i in 0..FRAGMENTS_PER_FRAME {
+        let (x, y, z) = sample_position(i);
+        total += noises.dune.get_noise_3d(x, y, z) + noises.rock.get_noise_3d(x, y, z);
+    }
+    total
+}
+
+fn time_frames(mut frame: impl FnMut() -> f32) -> Duration {
+    // Un frame de calentamiento antes de medir
+    black_box(frame());
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        black_box(frame());
+    }
+    start.elapsed() / FRAMES
+}
+
+fn main() {
+    let before = time_frames(frame_per_fragment);
+
+    let noises = RockyNoise::default();
+    let after = time_frames(|| frame_cached(&noises));
+
+    println!("{} fragmentos por frame, promedio de {} frames", FRAGMENTS_PER_FRAME, FRAMES);
+    println!("antes (ruido por fragmento):   {:>8.2} ms/frame", before.as_secs_f64() * 1000.0);
+    println!("después (ruido en caché):      {:>8.2} ms/frame", after.as_secs_f64() * 1000.0);
+    println!("aceleración: {:.2}x", before.as_secs_f64() / after.as_secs_f64());
+}
@@ -0,0 +1,575 @@
+// bvh.rs
+// Jerarquía de volúmenes envolventes (BVH) sobre los triángulos de una malla, en espacio de objeto.
+// Cada nodo guarda una caja alineada a los ejes que contiene a todos sus triángulos, así que una
+// consulta descarta de una vez las ramas que no toca. Se usa para lanzar rayos contra la malla y
+// para encontrar el punto de la superficie más cercano a una esfera o barrer una esfera contra la
+// malla (colisiones de la nave).
+use nalgebra_glm::Vec3;
+use crate::vertex::Vertex;
+
+// Triángulos máximos por hoja; por debajo de esto recorrer la lista es más barato que dividir
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+// Por debajo de este largo del producto cruz el triángulo no tiene área (sus vértices están en una
+// línea o repetidos) y su normal no existe
+const MIN_CROSS_LENGTH: f32 = 1e-12;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+}
+
+impl Triangle {
+    fn centroid(&self) -> Vec3 {
+        (self.a + self.b + self.c) / 3.0
+    }
+
+    // None si el triángulo no tiene área
+    fn try_normal(&self) -> Option<Vec3> {
+        (self.b - self.a).cross(&(self.c - self.a)).try_normalize(MIN_CROSS_LENGTH)
+    }
+
+    // Solo para triángulos de la BVH, que ya descartó los que no tienen área
+    fn normal(&self) -> Vec3 {
+        self.try_normal().unwrap_or_else(|| Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    // Punto del triángulo más cercano a `p` (Ericson, Real-Time Collision Detection 5.1.5)
+    fn closest_point(&self, p: &Vec3) -> Vec3 {
+        let (a, b, c) = (self.a, self.b, self.c);
+        let ab = b - a;
+        let ac = c - a;
+        let ap = p - a;
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return a;
+        }
+
+        let bp = p - b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = p - c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denominator = 1.0 / (va + vb + vc);
+        a + ab * (vb * denominator) + ac * (vc * denominator)
+    }
+
+    // Distancia a lo largo del rayo hasta el triángulo, de ambos lados (Möller-Trumbore)
+    fn ray_distance(&self, origin: &Vec3, direction: &Vec3) -> Option<f32> {
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+        let p = direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-8 {
+            return None;
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = origin - self.a;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+
+    // Distancia a lo largo del rayo a la que una esfera de radio `radius` centrada en `origin`
+    // toca el triángulo. Es un rayo contra el triángulo engordado por el radio: las dos caras
+    // desplazadas, un cilindro por arista y una esfera por vértice.
+    fn sphere_distance(&self, origin: &Vec3, direction: &Vec3, radius: f32) -> Option<f32> {
+        if (self.closest_point(origin) - origin).magnitude_squared() <= radius * radius {
+            return Some(0.0);
+        }
+
+        let mut best: Option<f32> = None;
+        let mut keep = |distance: Option<f32>| {
+            if let Some(distance) = distance {
+                if best.is_none_or(|closest| distance < closest) {
+                    best = Some(distance);
+                }
+            }
+        };
+
+        // Cara: la esfera toca el plano con un punto que cae dentro del triángulo
+        if let Some(normal) = self.try_normal() {
+            let height = (origin - self.a).dot(&normal);
+            let side = if height > 0.0 { 1.0 } else { -1.0 };
+            let approach = direction.dot(&normal) * side;
+            if height.abs() > radius && approach < 0.0 {
+                let t = (height.abs() - radius) / -approach;
+                let point = origin + direction * t - normal * (side * radius);
+                if (self.closest_point(&point) - point).magnitude_squared() <= 1e-8 {
+                    keep(Some(t));
+                }
+            }
+        }
+
+        for (start, end) in [(self.a, self.b), (self.b, self.c), (self.c, self.a)] {
+            keep(ray_cylinder(origin, direction, &start, &end, radius));
+            keep(ray_sphere(origin, direction, &start, radius));
+        }
+        best
+    }
+}
+
+// Distancia a la que el rayo entra a la esfera, o None si no la toca
+fn ray_sphere(origin: &Vec3, direction: &Vec3, center: &Vec3, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.magnitude_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 || (c > 0.0 && b > 0.0) {
+        return None;
+    }
+    Some((-b - discriminant.sqrt()).max(0.0))
+}
+
+// Distancia a la que el rayo entra al cilindro alrededor del segmento [start, end] (sin tapas; las
+// tapas las cubren las esferas de los vértices)
+fn ray_cylinder(origin: &Vec3, direction: &Vec3, start: &Vec3, end: &Vec3, radius: f32) -> Option<f32> {
+    let axis = end - start;
+    let length = axis.magnitude();
+    if length == 0.0 {
+        return None;
+    }
+    let axis = axis / length;
+
+    // Todo se mide en el plano perpendicular al eje
+    let offset = origin - start;
+    let offset_across = offset - axis * offset.dot(&axis);
+    let direction_across = direction - axis * direction.dot(&axis);
+    let a = direction_across.magnitude_squared();
+    if a < 1e-12 {
+        return None;
+    }
+    let b = offset_across.dot(&direction_across);
+    let c = offset_across.magnitude_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 || (c > 0.0 && b > 0.0) {
+        return None;
+    }
+
+    let t = ((-b - discriminant.sqrt()) / a).max(0.0);
+    let along = (offset + direction * t).dot(&axis);
+    (0.0..=length).contains(&along).then_some(t)
+}
+
+// Caja alineada a los ejes
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Aabb {
+            min: Vec3::repeat(f32::INFINITY),
+            max: Vec3::repeat(f32::NEG_INFINITY),
+        }
+    }
+
+    fn grow(&mut self, point: &Vec3) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    fn expanded(&self, margin: f32) -> Self {
+        Aabb {
+            min: self.min.add_scalar(-margin),
+            max: self.max.add_scalar(margin),
+        }
+    }
+
+    fn distance_squared_to(&self, point: &Vec3) -> f32 {
+        let closest = point.sup(&self.min).inf(&self.max);
+        (closest - point).magnitude_squared()
+    }
+
+    // Distancia de entrada del rayo a la caja (método de las losas), o None si no la toca antes de `max_distance`
+    fn ray_entry(&self, origin: &Vec3, inverse_direction: &Vec3, max_distance: f32) -> Option<f32> {
+        let mut near: f32 = 0.0;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            // Rayo paralelo a este eje: solo importa si el origen está entre las dos caras
+            // (evita 0 * infinito cuando el origen cae justo sobre una cara)
+            if inverse_direction[axis].is_infinite() {
+                if origin[axis] < self.min[axis] || origin[axis] > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (self.min[axis] - origin[axis]) * inverse_direction[axis];
+            let t2 = (self.max[axis] - origin[axis]) * inverse_direction[axis];
+            near = near.max(t1.min(t2));
+            far = far.min(t1.max(t2));
+        }
+        (near <= far).then_some(near)
+    }
+}
+
+enum NodeKind {
+    Leaf { first: usize, count: usize }, // Rango en `Bvh::triangles`
+    Inner { left: usize, right: usize }, // Índices de los hijos en `Bvh::nodes`
+}
+
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+// Impacto de un rayo contra la malla
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub distance: f32,
+    pub normal: Vec3, // Normal geométrica del triángulo, orientada contra el rayo
+}
+
+// Punto de la superficie más cercano a una esfera que la toca
+#[derive(Clone, Copy, Debug)]
+pub struct SurfaceContact {
+    pub point: Vec3,
+    pub distance: f32,
+}
+
+pub struct Bvh {
+    nodes: Vec<Node>,
+    triangles: Vec<Triangle>,
+}
+
+impl Bvh {
+    // Construye la jerarquía a partir de la lista de vértices (cada tres vértices forman un triángulo).
+    // Los triángulos sin área que traiga el OBJ se descartan: no tienen normal y no tapan nada.
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        let triangles: Vec<Triangle> = vertices
+            .chunks_exact(3)
+            .map(|triangle| Triangle {
+                a: triangle[0].position,
+                b: triangle[1].position,
+                c: triangle[2].position,
+            })
+            .filter(|triangle| triangle.try_normal().is_some())
+            .collect();
+
+        let mut bvh = Bvh { nodes: Vec::new(), triangles };
+        if !bvh.triangles.is_empty() {
+            bvh.build(0, bvh.triangles.len());
+        }
+        bvh
+    }
+
+    // Crea el nodo de los triángulos [first, first + count) y devuelve su índice. Divide por la
+    // mediana de los centroides sobre el eje más largo.
+    fn build(&mut self, first: usize, count: usize) -> usize {
+        let range = first..first + count;
+        let mut bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for triangle in &self.triangles[range.clone()] {
+            bounds.grow(&triangle.a);
+            bounds.grow(&triangle.b);
+            bounds.grow(&triangle.c);
+            centroid_bounds.grow(&triangle.centroid());
+        }
+
+        let index = self.nodes.len();
+        self.nodes.push(Node { bounds, kind: NodeKind::Leaf { first, count } });
+        if count <= MAX_LEAF_TRIANGLES {
+            return index;
+        }
+
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+        let half = count / 2;
+        self.triangles[range].select_nth_unstable_by(half, |p, q| {
+            p.centroid()[axis].total_cmp(&q.centroid()[axis])
+        });
+
+        let left = self.build(first, half);
+        let right = self.build(first + half, count - half);
+        self.nodes[index].kind = NodeKind::Inner { left, right };
+        index
+    }
+
+    // Primer impacto de un rayo contra la malla a menos de `max_distance`. `direction` debe estar normalizada.
+    pub fn ray_cast(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = direction.map(|component| 1.0 / component);
+        let mut best: Option<(f32, &Triangle)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let limit = best.map_or(max_distance, |(distance, _)| distance);
+            let node = &self.nodes[index];
+            if node.bounds.ray_entry(origin, &inverse_direction, limit).is_none() {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for triangle in &self.triangles[first..first + count] {
+                        if let Some(distance) = triangle.ray_distance(origin, direction) {
+                            if distance <= limit && best.is_none_or(|(closest, _)| distance < closest) {
+                                best = Some((distance, triangle));
+                            }
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        best.map(|(distance, triangle)| {
+            let normal = triangle.normal();
+            RayHit {
+                distance,
+                normal: if normal.dot(direction) > 0.0 { -normal } else { normal },
+            }
+        })
+    }
+
+    // Primer contacto de una esfera de radio `radius` que avanza desde `origin` en la dirección
+    // `direction` (normalizada) a lo más `max_distance`. Las cajas se engordan por el radio, así que
+    // no se salta ningún triángulo que la esfera roce en el camino.
+    pub fn sphere_cast(&self, origin: &Vec3, direction: &Vec3, radius: f32, max_distance: f32) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = direction.map(|component| 1.0 / component);
+        let mut best: Option<(f32, &Triangle)> = None;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let limit = best.map_or(max_distance, |(distance, _)| distance);
+            let node = &self.nodes[index];
+            if node.bounds.expanded(radius).ray_entry(origin, &inverse_direction, limit).is_none() {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for triangle in &self.triangles[first..first + count] {
+                        if let Some(distance) = triangle.sphere_distance(origin, direction, radius) {
+                            if distance <= limit && best.is_none_or(|(closest, _)| distance < closest) {
+                                best = Some((distance, triangle));
+                            }
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+
+        // La normal va del punto tocado al centro de la esfera en el contacto (en una arista o un
+        // vértice no es la normal de la cara)
+        best.map(|(distance, triangle)| {
+            let center = origin + direction * distance;
+            let away = center - triangle.closest_point(&center);
+            let normal = if away.magnitude_squared() > 0.0 {
+                away.normalize()
+            } else {
+                let normal = triangle.normal();
+                if normal.dot(direction) > 0.0 { -normal } else { normal }
+            };
+            RayHit { distance, normal }
+        })
+    }
+
+    // Punto de la superficie más cercano a `center`, si está a menos de `radius`
+    pub fn closest_point_within(&self, center: &Vec3, radius: f32) -> Option<SurfaceContact> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best: Option<SurfaceContact> = None;
+        let mut best_squared = radius * radius;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.distance_squared_to(center) > best_squared {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for triangle in &self.triangles[first..first + count] {
+                        let point = triangle.closest_point(center);
+                        let distance_squared = (point - center).magnitude_squared();
+                        if distance_squared <= best_squared {
+                            best_squared = distance_squared;
+                            best = Some(SurfaceContact { point, distance: distance_squared.sqrt() });
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    fn triangle() -> Triangle {
+        Triangle {
+            a: Vec3::new(0.0, 0.0, 0.0),
+            b: Vec3::new(1.0, 0.0, 0.0),
+            c: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    // Cuadrado de lado 2 en el plano z = 0, centrado en el origen
+    fn square() -> Bvh {
+        let corners = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(-1.0, 1.0, 0.0),
+        ];
+        let vertices: Vec<Vertex> = [0, 1, 2, 0, 2, 3]
+            .iter()
+            .map(|&index| Vertex::new(corners[index], Vec3::zeros(), Vec2::zeros()))
+            .collect();
+        Bvh::from_vertices(&vertices)
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).magnitude() < 1e-6, "{actual:?} != {expected:?}");
+    }
+
+    #[test]
+    fn closest_point_in_vertex_regions() {
+        let triangle = triangle();
+        assert_close(triangle.closest_point(&Vec3::new(-1.0, -1.0, 0.5)), triangle.a);
+        assert_close(triangle.closest_point(&Vec3::new(2.0, -0.5, -0.5)), triangle.b);
+        assert_close(triangle.closest_point(&Vec3::new(-0.5, 2.0, 1.0)), triangle.c);
+    }
+
+    #[test]
+    fn closest_point_in_edge_regions() {
+        let triangle = triangle();
+        assert_close(triangle.closest_point(&Vec3::new(0.5, -1.0, 0.3)), Vec3::new(0.5, 0.0, 0.0));
+        assert_close(triangle.closest_point(&Vec3::new(-1.0, 0.5, 0.3)), Vec3::new(0.0, 0.5, 0.0));
+        assert_close(triangle.closest_point(&Vec3::new(1.0, 1.0, -0.3)), Vec3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn closest_point_in_face_region() {
+        let triangle = triangle();
+        assert_close(triangle.closest_point(&Vec3::new(0.25, 0.25, 2.0)), Vec3::new(0.25, 0.25, 0.0));
+    }
+
+    #[test]
+    fn ray_cast_hits_the_nearest_face() {
+        let hit = square().ray_cast(&Vec3::new(0.5, 0.5, 3.0), &Vec3::new(0.0, 0.0, -1.0), 10.0).unwrap();
+        assert!((hit.distance - 3.0).abs() < 1e-6);
+        assert_close(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_cast_misses() {
+        let bvh = square();
+        // Pasa al lado, apunta hacia afuera y se queda corto
+        assert!(bvh.ray_cast(&Vec3::new(2.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 10.0).is_none());
+        assert!(bvh.ray_cast(&Vec3::new(0.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, 1.0), 10.0).is_none());
+        assert!(bvh.ray_cast(&Vec3::new(0.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 2.0).is_none());
+    }
+
+    #[test]
+    fn degenerate_faces_are_dropped() {
+        // Un triángulo bueno y dos sin área: vértices en línea y un vértice repetido
+        let positions = [
+            Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(2.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 1.0, 2.0),
+        ];
+        let vertices: Vec<Vertex> = positions.iter().map(|&position| Vertex::new(position, Vec3::zeros(), Vec2::zeros())).collect();
+        let bvh = Bvh::from_vertices(&vertices);
+        assert_eq!(bvh.triangles.len(), 1);
+
+        // El rayo pasa por la arista sin área antes de llegar al triángulo bueno
+        let hit = bvh.ray_cast(&Vec3::new(0.5, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 10.0).unwrap();
+        assert!((hit.distance - 3.0).abs() < 1e-6);
+        assert_close(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+
+        let hit = bvh.sphere_cast(&Vec3::new(0.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 0.25, 10.0).unwrap();
+        assert!(hit.normal.iter().all(|component| component.is_finite()));
+        let contact = bvh.closest_point_within(&Vec3::new(0.0, 0.0, 0.1), 0.5).unwrap();
+        assert!(contact.distance.is_finite());
+
+        // Una malla hecha solo de caras sin área no tiene nada contra qué chocar
+        let bvh = Bvh::from_vertices(&vertices[3..]);
+        assert!(bvh.ray_cast(&Vec3::new(1.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 10.0).is_none());
+        assert!(bvh.closest_point_within(&Vec3::new(1.0, 0.0, 1.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn sphere_cast_touches_the_face() {
+        let hit = square().sphere_cast(&Vec3::new(0.0, 0.0, 3.0), &Vec3::new(0.0, 0.0, -1.0), 0.5, 10.0).unwrap();
+        assert!((hit.distance - 2.5).abs() < 1e-5);
+        assert_close(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn sphere_cast_grazes_an_edge_that_a_ray_misses() {
+        // El centro pasa a 0.2 del borde x = 1, pero la esfera tiene radio 0.5
+        let bvh = square();
+        let origin = Vec3::new(1.2, 0.0, 3.0);
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        assert!(bvh.ray_cast(&origin, &direction, 10.0).is_none());
+
+        let hit = bvh.sphere_cast(&origin, &direction, 0.5, 10.0).unwrap();
+        let expected = 3.0 - (0.5f32 * 0.5 - 0.2 * 0.2).sqrt();
+        assert!((hit.distance - expected).abs() < 1e-5);
+        assert!(hit.normal.x > 0.0 && hit.normal.z > 0.0);
+        assert!(bvh.sphere_cast(&Vec3::new(1.6, 0.0, 3.0), &direction, 0.5, 10.0).is_none());
+    }
+}
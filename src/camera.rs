@@ -1,6 +1,26 @@
-use nalgebra_glm::{Vec3, rotate_vec3, look_at};
+use nalgebra_glm::{Mat3, Quat, Vec3, look_at, mat3_to_quat, quat_angle_axis, quat_normalize, quat_rotate_vec3};
 use std::f32::consts::PI;
 
+// Ejes locales de la cámara en el modo de vuelo libre: mira hacia -Z con +Y arriba
+const LOCAL_FORWARD: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+const LOCAL_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+const LOCAL_RIGHT: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+
+// Campo de visión vertical por defecto en radianes (el que siempre ha usado la proyección)
+pub const DEFAULT_FOV: f32 = 1.0;
+
+// Velocidad de traslación del vuelo libre en unidades por segundo
+const FREE_FLY_SPEED: f32 = 20.0;
+
+// Velocidad de giro del vuelo libre en radianes por segundo
+const FREE_FLY_TURN_SPEED: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    Orbit,   // Orbita alrededor de `center` con yaw y pitch (el pitch está limitado)
+    FreeFly, // Se mueve sobre sus propios ejes y gira libremente, incluso de cabeza o alabeando
+}
+
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
@@ -11,10 +31,16 @@ pub struct Camera {
     pub is_bird_eye_view: bool,
     pub default_eye: Vec3,    // Posición inicial de la cámara
     pub default_center: Vec3, // Centro inicial de la cámara
+    pub default_up: Vec3,     // Vector "arriba" inicial (la cámara de persecución lo cambia al alabear)
     pub original_eye: Vec3,   // Para almacenar la posición de la cámara antes del Bird Eye View
     pub original_center: Vec3, // Para almacenar el centro antes del Bird Eye View
     pub original_yaw: f32,    // Para almacenar el yaw antes del Bird Eye View
     pub original_pitch: f32,  // Para almacenar el pitch antes del Bird Eye View
+    pub follow_target: Option<usize>, // Modelo al que está bloqueada la cámara (None en modo libre)
+    pub mode: CameraMode,
+    pub orientation: Quat, // Orientación del vuelo libre; se toma de la vista actual al entrar al modo
+    pub fov: f32,          // Campo de visión vertical en radianes
+    pub pan_offset: Vec3,  // Desplazamiento del centro respecto a lo que sigue la cámara orbital (arrastre con el botón derecho)
 }
 
 impl Camera {
@@ -29,10 +55,16 @@ impl Camera {
             is_bird_eye_view: false,
             default_eye: eye,
             default_center: center,
+            default_up: up,
             original_eye: eye,
             original_center: center,
             original_yaw: 0.0,
             original_pitch: 0.0,
+            follow_target: None,
+            mode: CameraMode::Orbit,
+            orientation: Quat::identity(),
+            fov: DEFAULT_FOV,
+            pan_offset: Vec3::zeros(),
         }
     }
 
@@ -59,20 +91,36 @@ impl Camera {
             self.is_bird_eye_view = true;
         }
 
+        self.pan_offset = Vec3::zeros();
         self.has_changed = true;
     }
 
-    pub fn get_view_matrix(&self) -> nalgebra_glm::Mat4 {
-        look_at(&self.eye, &self.center, &self.up)
+    // Bloquea la cámara en un modelo: el centro pasa a su posición y la cámara se queda donde está
+    pub fn lock_on(&mut self, target: usize, position: Vec3) {
+        self.mode = CameraMode::Orbit;
+        self.follow_target = Some(target);
+        self.pan_offset = Vec3::zeros();
+        self.center = position;
+        self.has_changed = true;
     }
 
-    pub fn basis_change(&self, vector: &Vec3) -> Vec3 {
-        let forward = (self.center - self.eye).normalize();
-        let right = forward.cross(&self.up).normalize();
-        let up = right.cross(&forward).normalize();
+    // Vuelve al modo libre
+    pub fn release(&mut self) {
+        self.follow_target = None;
+        self.pan_offset = Vec3::zeros();
+    }
 
-        let rotated = vector.x * right + vector.y * up - vector.z * forward;
-        rotated.normalize()
+    // Mueve la cámara junto con el modelo bloqueado, conservando la distancia y el ángulo desde los
+    // que se lo mira (los que el usuario eligió al orbitar y hacer zoom)
+    pub fn track(&mut self, position: Vec3) {
+        let offset = self.eye - self.center;
+        self.center = position + self.pan_offset;
+        self.eye = self.center + offset;
+        self.has_changed = true;
+    }
+
+    pub fn get_view_matrix(&self) -> nalgebra_glm::Mat4 {
+        look_at(&self.eye, &self.center, &self.up)
     }
 
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
@@ -101,22 +149,83 @@ impl Camera {
         self.has_changed = true;
     }
 
-    pub fn move_center(&mut self, direction: Vec3) {
-        let radius_vector = self.center - self.eye;
-        let radius = radius_vector.magnitude();
+    pub fn get_forward_vector(&self) -> Vec3 {
+        (self.center - self.eye).normalize()
+    }
+
+    // Pasa al vuelo libre partiendo de la vista actual, así que la imagen no salta
+    pub fn enter_free_fly(&mut self) {
+        let forward = self.get_forward_vector();
+        let right = forward.cross(&self.up).normalize();
+        let up = right.cross(&forward);
+
+        // Las columnas son los ejes locales (derecha, arriba, atrás) en espacio de mundo
+        self.orientation = mat3_to_quat(&Mat3::from_columns(&[right, up, -forward]));
+        self.mode = CameraMode::FreeFly;
+        self.follow_target = None;
+        self.pan_offset = Vec3::zeros();
+        self.is_bird_eye_view = false;
+    }
+
+    // Vuelve al modo orbital. La cámara se queda donde está y conserva su alabeo hasta que otro
+    // modo cambie el "arriba"
+    pub fn leave_free_fly(&mut self) {
+        self.mode = CameraMode::Orbit;
+        self.pan_offset = Vec3::zeros();
+    }
+
+    // Vuelo libre durante `dt` segundos. `translation` va en ejes de la cámara (x derecha, y arriba,
+    // z adelante) y `turn` es cabeceo, guiñada y alabeo (-1 a 1 cada uno).
+    pub fn fly(&mut self, translation: Vec3, turn: Vec3, dt: f32) {
+        self.rotate(turn * (FREE_FLY_TURN_SPEED * dt));
+
+        let forward = quat_rotate_vec3(&self.orientation, &LOCAL_FORWARD);
+        let up = quat_rotate_vec3(&self.orientation, &LOCAL_UP);
+        let right = quat_rotate_vec3(&self.orientation, &LOCAL_RIGHT);
+        self.translate((right * translation.x + up * translation.y + forward * translation.z) * (FREE_FLY_SPEED * dt));
+    }
 
-        let angle_x = direction.x * 0.05;
-        let angle_y = direction.y * 0.05;
+    // Gira la cámara del vuelo libre sobre sus propios ejes: cabeceo, guiñada y alabeo en radianes
+    pub fn rotate(&mut self, angles: Vec3) {
+        self.orientation = quat_normalize(
+            &(self.orientation
+                * quat_angle_axis(angles.x, &LOCAL_RIGHT)
+                * quat_angle_axis(angles.y, &LOCAL_UP)
+                * quat_angle_axis(angles.z, &LOCAL_FORWARD)),
+        );
 
-        let rotated = rotate_vec3(&radius_vector, angle_x, &Vec3::new(0.0, 1.0, 0.0));
-        let right = rotated.cross(&self.up).normalize();
-        let final_rotated = rotate_vec3(&rotated, angle_y, &right);
+        // El centro queda a la misma distancia frente a la cámara (al menos 1, para que no quede
+        // sobre el ojo si el zoom llegó hasta el centro)
+        let forward = quat_rotate_vec3(&self.orientation, &LOCAL_FORWARD);
+        let distance = (self.center - self.eye).magnitude().max(1.0);
+        self.center = self.eye + forward * distance;
+        self.up = quat_rotate_vec3(&self.orientation, &LOCAL_UP);
 
-        self.center = self.eye + final_rotated.normalize() * radius;
+        // Yaw y pitch se mantienen al día para lo que todavía los usa
+        self.pitch = forward.y.atan2((forward.x.powi(2) + forward.z.powi(2)).sqrt());
+        self.yaw = forward.z.atan2(forward.x);
         self.has_changed = true;
     }
 
-    pub fn get_forward_vector(&self) -> Vec3 {
-        (self.center - self.eye).normalize()
+    // Desplaza la cámara y su centro sobre el plano de la vista, en unidades de mundo. En el modo
+    // orbital el desplazamiento se conserva aunque el centro siga a la nave o a un cuerpo.
+    pub fn pan(&mut self, right_amount: f32, up_amount: f32) {
+        let forward = self.get_forward_vector();
+        let right = forward.cross(&self.up).normalize();
+        let up = right.cross(&forward);
+        let offset = right * right_amount + up * up_amount;
+        self.translate(offset);
+        self.pan_offset += offset;
+    }
+
+    // Mueve la cámara y su centro hacia adelante (hacia atrás si `distance` es negativa)
+    pub fn dolly(&mut self, distance: f32) {
+        self.translate(self.get_forward_vector() * distance);
+    }
+
+    fn translate(&mut self, offset: Vec3) {
+        self.eye += offset;
+        self.center += offset;
+        self.has_changed = true;
     }
 }
@@ -0,0 +1,269 @@
+// camera_path.rs
+// Recorridos de cámara para grabar videos de demostración. Una ruta es una lista de cuadros clave
+// (posición, punto al que se mira, campo de visión y tiempo); al reproducirla la cámara pasa por
+// todos ellos siguiendo una spline de Catmull-Rom, que es suave y pasa exactamente por cada cuadro.
+// Los cuadros se graban desde la pose actual de la cámara y la ruta se guarda en un archivo TOML,
+// donde se pueden ajustar a mano los tiempos y el campo de visión.
+use std::fmt;
+use std::fs;
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::camera::Camera;
+
+// Archivo donde se guarda la ruta; si existe se carga al iniciar
+pub const DEFAULT_CAMERA_PATH: &str = "src/assets/camera_path.toml";
+
+// Segundos entre un cuadro grabado y el siguiente
+const KEYFRAME_INTERVAL: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    pub time: f32,        // Segundos desde el inicio del recorrido
+    pub eye: [f32; 3],    // Posición de la cámara
+    pub target: [f32; 3], // Punto al que mira
+    pub fov: f32,         // Campo de visión vertical en radianes
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraPathFile {
+    #[serde(default)]
+    keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug)]
+pub enum CameraPathError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, message: String },
+    Serialize { path: String, message: String },
+    Invalid { entry: String, message: String },
+}
+
+impl fmt::Display for CameraPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CameraPathError::Io { path, source } => write!(f, "Failed to access camera path {}: {}", path, source),
+            CameraPathError::Parse { path, message } => write!(f, "Failed to parse camera path {}: {}", path, message),
+            CameraPathError::Serialize { path, message } => write!(f, "Failed to serialize camera path for {}: {}", path, message),
+            CameraPathError::Invalid { entry, message } => write!(f, "Invalid camera path entry {}: {}", entry, message),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    playback: Option<f32>, // Segundos reproducidos, mientras se reproduce
+}
+
+impl CameraPath {
+    // Carga la ruta guardada; si el archivo no existe la ruta empieza vacía
+    pub fn load(path: &str) -> Result<Self, CameraPathError> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(CameraPath::default()),
+            Err(source) => return Err(CameraPathError::Io { path: path.to_string(), source }),
+        };
+        let file: CameraPathFile = toml::from_str(&source).map_err(|error| CameraPathError::Parse {
+            path: path.to_string(),
+            message: error.to_string(),
+        })?;
+
+        // Los tiempos deben crecer para saber entre qué cuadros cae cada instante
+        for (index, pair) in file.keyframes.windows(2).enumerate() {
+            if pair[1].time <= pair[0].time {
+                return Err(CameraPathError::Invalid {
+                    entry: format!("keyframes[{}]", index + 1),
+                    message: format!("time must be greater than the previous keyframe's, got {}", pair[1].time),
+                });
+            }
+        }
+        for (index, keyframe) in file.keyframes.iter().enumerate() {
+            if !(keyframe.fov > 0.0 && keyframe.fov < std::f32::consts::PI) {
+                return Err(CameraPathError::Invalid {
+                    entry: format!("keyframes[{}]", index),
+                    message: format!("fov must be between 0 and PI radians, got {}", keyframe.fov),
+                });
+            }
+        }
+
+        Ok(CameraPath { keyframes: file.keyframes, playback: None })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CameraPathError> {
+        let file = CameraPathFile { keyframes: self.keyframes.clone() };
+        let source = toml::to_string_pretty(&file).map_err(|error| CameraPathError::Serialize {
+            path: path.to_string(),
+            message: error.to_string(),
+        })?;
+        fs::write(path, source).map_err(|source| CameraPathError::Io { path: path.to_string(), source })
+    }
+
+    // Agrega la pose actual de la cámara como cuadro clave, `KEYFRAME_INTERVAL` segundos después del último
+    pub fn record(&mut self, camera: &Camera) {
+        let time = self.keyframes.last().map_or(0.0, |last| last.time + KEYFRAME_INTERVAL);
+        self.keyframes.push(Keyframe {
+            time,
+            eye: camera.eye.into(),
+            target: camera.center.into(),
+            fov: camera.fov,
+        });
+    }
+
+    // Borra todos los cuadros (y detiene la reproducción)
+    pub fn clear(&mut self, camera: &mut Camera) {
+        self.stop(camera);
+        self.keyframes.clear();
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    // Empieza a reproducir desde el principio, o detiene la reproducción. Hacen falta al menos dos
+    // cuadros. Devuelve true solo si la reproducción empezó.
+    pub fn toggle_playback(&mut self, camera: &mut Camera) -> bool {
+        if self.is_playing() {
+            self.stop(camera);
+        } else if self.keyframes.len() >= 2 {
+            self.playback = Some(0.0);
+            camera.release();
+            camera.leave_free_fly();
+            return true;
+        }
+        false
+    }
+
+    // Termina la reproducción: la cámara queda en vuelo libre donde está, sin saltos, y conserva el
+    // campo de visión de la ruta (al llegar al final, el del último cuadro)
+    fn stop(&mut self, camera: &mut Camera) {
+        if self.playback.take().is_some() {
+            camera.enter_free_fly();
+        }
+    }
+
+    // Fracción reproducida, de 0 a 1
+    pub fn progress(&self) -> f32 {
+        match (self.playback, self.keyframes.first(), self.keyframes.last()) {
+            (Some(elapsed), Some(first), Some(last)) if last.time > first.time => {
+                (elapsed / (last.time - first.time)).min(1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Avanza la reproducción `frame_dt` segundos reales y coloca la cámara sobre la ruta. Al llegar
+    // al final la cámara queda en vuelo libre en el último cuadro.
+    pub fn advance(&mut self, camera: &mut Camera, frame_dt: f32) {
+        let elapsed = match &mut self.playback {
+            Some(elapsed) => {
+                *elapsed += frame_dt;
+                *elapsed
+            }
+            None => return,
+        };
+
+        let start = self.keyframes[0].time;
+        let (eye, target, fov) = self.sample(start + elapsed);
+        camera.eye = eye;
+        camera.center = target;
+        camera.fov = fov;
+        camera.up = camera.default_up;
+        camera.has_changed = true;
+
+        if start + elapsed >= self.keyframes[self.keyframes.len() - 1].time {
+            self.stop(camera);
+        }
+    }
+
+    // Pose de la ruta en el instante `time`: posición, punto al que se mira y campo de visión
+    fn sample(&self, time: f32) -> (Vec3, Vec3, f32) {
+        let last = self.keyframes.len() - 1;
+        let segment = self.keyframes[..last]
+            .iter()
+            .rposition(|keyframe| keyframe.time <= time)
+            .unwrap_or(0);
+
+        // Los extremos se repiten para que la curva empiece y termine en el primer y el último cuadro
+        let k0 = &self.keyframes[segment.saturating_sub(1)];
+        let k1 = &self.keyframes[segment];
+        let k2 = &self.keyframes[segment + 1];
+        let k3 = &self.keyframes[(segment + 2).min(last)];
+        let t = ((time - k1.time) / (k2.time - k1.time)).clamp(0.0, 1.0);
+
+        let eye = catmull_rom(k0.eye.into(), k1.eye.into(), k2.eye.into(), k3.eye.into(), t);
+        let target = catmull_rom(k0.target.into(), k1.target.into(), k2.target.into(), k3.target.into(), t);
+        let fov = catmull_rom(Vec3::repeat(k0.fov), Vec3::repeat(k1.fov), Vec3::repeat(k2.fov), Vec3::repeat(k3.fov), t).x;
+        (eye, target, fov.clamp(0.01, std::f32::consts::PI - 0.01))
+    }
+}
+
+// Spline de Catmull-Rom uniforme: pasa por `p1` en t = 0 y por `p2` en t = 1, con tangentes tomadas de los vecinos
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, eye: [f32; 3], target: [f32; 3], fov: f32) -> Keyframe {
+        Keyframe { time, eye, target, fov }
+    }
+
+    #[test]
+    fn spline_passes_through_every_keyframe() {
+        let path = CameraPath {
+            keyframes: vec![
+                keyframe(0.0, [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.8),
+                keyframe(2.0, [5.0, 1.0, -3.0], [0.0, 0.0, 0.0], 1.2),
+                keyframe(3.5, [8.0, -2.0, 4.0], [2.0, 1.0, 0.0], 0.5),
+                keyframe(7.0, [0.0, 6.0, 1.0], [-1.0, 0.0, 3.0], 1.0),
+            ],
+            playback: None,
+        };
+
+        for keyframe in &path.keyframes {
+            let (eye, target, fov) = path.sample(keyframe.time);
+            assert!((eye - Vec3::from(keyframe.eye)).magnitude() < 1e-5, "eye at {}", keyframe.time);
+            assert!((target - Vec3::from(keyframe.target)).magnitude() < 1e-5, "target at {}", keyframe.time);
+            assert!((fov - keyframe.fov).abs() < 1e-5, "fov at {}", keyframe.time);
+        }
+    }
+
+    #[test]
+    fn load_rejects_times_that_do_not_increase() {
+        let path = std::env::temp_dir().join(format!("camera_path_test_{}.toml", std::process::id()));
+        let write = |second_time: f32| {
+            let source = format!(
+                "[[keyframes]]\ntime = 1.0\neye = [0.0, 0.0, 0.0]\ntarget = [1.0, 0.0, 0.0]\nfov = 0.8\n\n\
+                 [[keyframes]]\ntime = {:.1}\neye = [1.0, 0.0, 0.0]\ntarget = [2.0, 0.0, 0.0]\nfov = 0.8\n",
+                second_time
+            );
+            fs::write(&path, source).unwrap();
+        };
+
+        write(1.0);
+        let equal = CameraPath::load(path.to_str().unwrap());
+        write(0.5);
+        let decreasing = CameraPath::load(path.to_str().unwrap());
+        write(2.0);
+        let increasing = CameraPath::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(equal, Err(CameraPathError::Invalid { entry, .. }) if entry == "keyframes[1]"));
+        assert!(matches!(decreasing, Err(CameraPathError::Invalid { .. })));
+        assert_eq!(increasing.unwrap().keyframe_count(), 2);
+    }
+}
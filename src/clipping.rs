@@ -0,0 +1,85 @@
+// clipping.rs
+// Recorte de triángulos en espacio homogéneo (clip space), entre el vertex shader y el ensamblado de primitivas.
+use nalgebra_glm::Vec4;
+use crate::vertex::Vertex;
+
+// Máximo de vértices que puede producir un triángulo recortado contra 6 planos
+pub const MAX_CLIPPED_VERTICES: usize = 9;
+
+// Planos del frustum en espacio de recorte. Un punto está dentro si dot(plano, posición) >= 0.
+// El rango de z es [-w, w] porque `perspective` de nalgebra_glm usa la convención de OpenGL.
+const FRUSTUM_PLANES: [Vec4; 6] = [
+    Vec4::new(0.0, 0.0, 1.0, 1.0),  // Cercano: z >= -w
+    Vec4::new(0.0, 0.0, -1.0, 1.0), // Lejano: z <= w
+    Vec4::new(1.0, 0.0, 0.0, 1.0),  // Izquierdo: x >= -w
+    Vec4::new(-1.0, 0.0, 0.0, 1.0), // Derecho: x <= w
+    Vec4::new(0.0, 1.0, 0.0, 1.0),  // Inferior: y >= -w
+    Vec4::new(0.0, -1.0, 0.0, 1.0), // Superior: y <= w
+];
+
+fn plane_distance(plane: &Vec4, vertex: &Vertex) -> f32 {
+    plane.dot(&vertex.clip_position)
+}
+
+// Resultado de recortar un triángulo
+pub enum Clipped {
+    Inside,  // No toca ningún plano: se usa el mismo triángulo, sin copiar sus vértices
+    Polygon, // Polígono recortado (de 3 a 9 vértices, en orden) en `output`
+    Outside, // Fuera del frustum
+}
+
+// Recorta un triángulo contra los seis planos del frustum (Sutherland-Hodgman). `output` y
+// `scratch` son buffers del llamador que se reutilizan entre triángulos para no reservar memoria.
+pub fn clip_triangle(triangle: &[Vertex; 3], output: &mut Vec<Vertex>, scratch: &mut Vec<Vertex>) -> Clipped {
+    // Aceptación y rechazo triviales: la gran mayoría de triángulos no tocan ningún plano
+    let mut all_inside = true;
+    for plane in &FRUSTUM_PLANES {
+        let d: [f32; 3] = [
+            plane_distance(plane, &triangle[0]),
+            plane_distance(plane, &triangle[1]),
+            plane_distance(plane, &triangle[2]),
+        ];
+        if d.iter().all(|&d| d < 0.0) {
+            return Clipped::Outside;
+        }
+        if d.iter().any(|&d| d < 0.0) {
+            all_inside = false;
+        }
+    }
+    if all_inside {
+        return Clipped::Inside;
+    }
+
+    output.clear();
+    output.extend_from_slice(triangle);
+    for plane in &FRUSTUM_PLANES {
+        if output.is_empty() {
+            return Clipped::Outside;
+        }
+        std::mem::swap(scratch, output);
+        output.clear();
+        let input = &*scratch;
+
+        for i in 0..input.len() {
+            let current = &input[i];
+            let next = &input[(i + 1) % input.len()];
+            let d_current = plane_distance(plane, current);
+            let d_next = plane_distance(plane, next);
+
+            if d_current >= 0.0 {
+                output.push(current.clone());
+            }
+
+            // El borde cruza el plano: agregar el punto de intersección
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                output.push(current.lerp(next, t));
+            }
+        }
+    }
+
+    if output.len() < 3 {
+        return Clipped::Outside;
+    }
+    Clipped::Polygon
+}
@@ -0,0 +1,106 @@
+// clock.rs
+// Reloj de simulación: mide el tiempo real entre frames y lo reparte en pasos fijos, de modo que
+// las órbitas, rotaciones y colisiones avanzan igual sin importar qué tan rápido rasteriza el frame.
+// Encima del tiempo real se aplica una escala de tiempo (pausa, cámara rápida y reversa): la escala
+// alarga cada paso en lugar de multiplicar la cantidad de pasos, así que correr a 100x cuesta lo mismo
+// por frame que correr a 1x. La nave no usa la escala: vuela siempre en tiempo real, también en pausa.
+use std::fmt;
+use std::time::Instant;
+
+// Duración de cada paso de la simulación en segundos
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+// Pasos máximos por frame. Si un frame tarda más, el tiempo que sobra se descarta en lugar de
+// acumularse, para que un frame lento no haga todavía más lento al siguiente.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+// Pasos que hay que dar en un frame
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSteps {
+    pub simulation: u32, // Pasos de `step_dt()` para los cuerpos (0 en pausa, salvo `step_once`)
+    pub real: u32,       // Pasos de `FIXED_TIMESTEP` en tiempo real, para la nave
+}
+
+pub struct SimulationClock {
+    last_instant: Instant,
+    accumulator: f32,
+    pending_steps: u32, // Pasos pedidos con `step_once` mientras está en pausa
+    pub time: f32,       // Tiempo simulado en segundos desde el inicio (baja en reversa)
+    pub frame_dt: f32,   // Tiempo real del último frame en segundos
+    pub time_scale: f32, // Segundos simulados por segundo real (1, 2, 10, 100...)
+    pub reversed: bool,
+    pub paused: bool,
+}
+
+impl SimulationClock {
+    pub fn new() -> Self {
+        SimulationClock {
+            last_instant: Instant::now(),
+            accumulator: 0.0,
+            pending_steps: 0,
+            time: 0.0,
+            frame_dt: 0.0,
+            time_scale: 1.0,
+            reversed: false,
+            paused: false,
+        }
+    }
+
+    // Mide el tiempo real desde el frame anterior y devuelve cuántos pasos fijos hay que simular
+    pub fn tick(&mut self) -> FrameSteps {
+        let now = Instant::now();
+        self.frame_dt = now.duration_since(self.last_instant).as_secs_f32();
+        self.last_instant = now;
+
+        self.accumulator += self.frame_dt;
+        let real = (self.accumulator / FIXED_TIMESTEP) as u32;
+        let real = if real > MAX_STEPS_PER_FRAME {
+            self.accumulator = 0.0;
+            MAX_STEPS_PER_FRAME
+        } else {
+            self.accumulator -= real as f32 * FIXED_TIMESTEP;
+            real
+        };
+
+        let simulation = if self.paused { std::mem::take(&mut self.pending_steps) } else { real };
+        self.time += simulation as f32 * self.step_dt();
+        FrameSteps { simulation, real }
+    }
+
+    // Tiempo simulado con signo de cada paso: `FIXED_TIMESTEP` de tiempo real por la escala de
+    // tiempo, negativo cuando la simulación corre en reversa
+    pub fn step_dt(&self) -> f32 {
+        let dt = FIXED_TIMESTEP * self.time_scale;
+        if self.reversed { -dt } else { dt }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    // Avanza un solo paso en el siguiente frame; solo tiene efecto en pausa
+    pub fn step_once(&mut self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale;
+    }
+
+    pub fn toggle_reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+}
+
+impl fmt::Display for SimulationClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = if self.reversed { "-" } else { "" };
+        write!(f, "t = {:.1} s, x{}{}", self.time, direction, self.time_scale)?;
+        if self.paused {
+            write!(f, " (pausa)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+// collision.rs
+// Colisiones continuas entre la nave y los cuerpos de la escena. En lugar de revisar si dos esferas
+// se tocan al final del paso (a alta velocidad la nave podría atravesar un planeta entre dos pasos),
+// se barre la esfera de la nave a lo largo de su movimiento contra la esfera de cada cuerpo, que
+// también se mueve durante el paso, y se busca el primer instante de contacto.
+// Los cuerpos con `collision = "mesh"` usan la esfera solo como fase amplia; si la nave la toca,
+// se revisa contra los triángulos de la malla con su BVH.
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use crate::bvh::Bvh;
+
+// Iteraciones máximas de choque por paso; después de cada choque se sigue con el tiempo que sobra
+const MAX_ITERATIONS: usize = 4;
+
+// Separación que se deja entre las esferas después de un choque para no quedar pegadas
+const SKIN: f32 = 1e-3;
+
+// Fracción de la velocidad normal que se conserva al rebotar (0 solo desliza, 1 rebote elástico)
+const RESTITUTION: f32 = 0.4;
+
+// Por debajo de esta velocidad de impacto la nave desliza sobre la superficie en lugar de rebotar
+const BOUNCE_MIN_SPEED: f32 = 1.0;
+
+// Contra una malla el movimiento se revisa en pasos de a lo más media esfera de la nave, hasta
+// este máximo; más rápido que eso se barre la esfera completa contra la BVH
+const MAX_MESH_SUBSTEPS: usize = 32;
+
+// Forma de colisión de un cuerpo (`collision = "sphere"` o `"mesh"` en la escena)
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionShape {
+    Sphere, // Esfera de radio `collision_radius`
+    Mesh,   // Triángulos de la malla (mallas que no son esferas, como eye.obj)
+}
+
+// Malla de un cuerpo en su pose al final del paso
+pub struct MeshCollider<'a> {
+    pub bvh: &'a Bvh,
+    pub world_matrix: Mat4, // Matriz de modelo (la escala debe ser uniforme)
+    pub inverse: Mat4,
+    pub scale: f32,
+}
+
+impl<'a> MeshCollider<'a> {
+    pub fn new(bvh: &'a Bvh, world_matrix: Mat4, scale: f32) -> Self {
+        MeshCollider {
+            bvh,
+            world_matrix,
+            inverse: world_matrix.try_inverse().unwrap_or_else(Mat4::identity),
+            scale,
+        }
+    }
+
+    fn point_to_local(&self, point: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    fn vector_to_local(&self, vector: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+
+    fn vector_to_world(&self, vector: &Vec3) -> Vec3 {
+        (self.world_matrix * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+}
+
+// Movimiento de un cuerpo durante el paso: su esfera va de `start` a `end`. Con malla, `radius`
+// es el de la esfera que envuelve a la malla y la malla se mueve junto con ella.
+pub struct BodySweep<'a> {
+    pub body: usize, // Índice del modelo en la lista de la escena
+    pub start: Vec3,
+    pub end: Vec3,
+    pub radius: f32,
+    pub mesh: Option<MeshCollider<'a>>,
+}
+
+impl BodySweep<'_> {
+    fn position_at(&self, fraction: f32) -> Vec3 {
+        self.start + (self.end - self.start) * fraction
+    }
+
+    // Primer contacto de una esfera que se mueve `motion` desde `position` en lo que queda del paso
+    // (`elapsed` es la fracción ya recorrida). Devuelve la fracción `t` de ese movimiento, la normal
+    // del contacto y la posición de la esfera ya separada de la superficie.
+    fn first_contact(&self, position: Vec3, motion: Vec3, radius: f32, elapsed: f32) -> Option<Contact> {
+        let body_start = self.position_at(elapsed);
+        let body_motion = self.end - body_start;
+        let offset = position - body_start;
+        let relative_motion = motion - body_motion;
+
+        // Fase amplia (o la colisión completa para las esferas)
+        let t = sweep_spheres(offset, relative_motion, radius + self.radius)?;
+        let mesh = match &self.mesh {
+            Some(mesh) => mesh,
+            None => {
+                let remaining = 1.0 - elapsed;
+                let body_center = self.position_at(elapsed + remaining * t);
+                let normal = safe_normalize(position + motion * t - body_center);
+                return Some(Contact {
+                    t,
+                    normal,
+                    position: body_center + normal * (radius + self.radius + SKIN),
+                });
+            }
+        };
+
+        // Fase precisa contra la malla, en espacio de objeto del cuerpo al final del paso. La nave
+        // se mueve relativa al cuerpo, así que su posición se desplaza con él.
+        let local_radius = radius / mesh.scale;
+        let local_at = |t: f32| mesh.point_to_local(&(offset + relative_motion * t + self.end));
+        let local_motion = mesh.vector_to_local(&relative_motion);
+        let length = local_motion.magnitude();
+        let substeps = (length / (local_radius * 0.5)).ceil() as usize;
+
+        if substeps <= MAX_MESH_SUBSTEPS {
+            let substeps = substeps.max(1);
+            for step in 0..=substeps {
+                let t = step as f32 / substeps as f32;
+                let local = local_at(t);
+                if let Some(contact) = mesh.bvh.closest_point_within(&local, local_radius) {
+                    let local_normal = local - contact.point;
+                    let normal = safe_normalize(mesh.vector_to_world(&local_normal));
+                    // Quedarse en el último punto sin contacto; si ya había contacto al inicio, empujar hacia afuera
+                    let (t, push) = if step == 0 {
+                        (0.0, (local_radius - contact.distance) * mesh.scale)
+                    } else {
+                        ((step - 1) as f32 / substeps as f32, 0.0)
+                    };
+                    return Some(Contact { t, normal, position: position + motion * t + normal * (push + SKIN) });
+                }
+            }
+            return None;
+        }
+
+        // Demasiado rápido para los pasos: barrer la esfera contra la malla (no la atraviesa aunque
+        // solo roce una arista o un vértice)
+        if let Some(contact) = mesh.bvh.closest_point_within(&local_at(0.0), local_radius) {
+            let normal = safe_normalize(mesh.vector_to_world(&(local_at(0.0) - contact.point)));
+            let push = (local_radius - contact.distance) * mesh.scale;
+            return Some(Contact { t: 0.0, normal, position: position + normal * (push + SKIN) });
+        }
+        let direction = local_motion / length;
+        mesh.bvh.sphere_cast(&local_at(0.0), &direction, local_radius, length).map(|hit| {
+            let t = hit.distance / length;
+            let normal = safe_normalize(mesh.vector_to_world(&hit.normal));
+            Contact { t, normal, position: position + motion * t + normal * SKIN }
+        })
+    }
+
+    // Distancia desde `origin` a lo largo de `direction` (normalizada) hasta el cuerpo en su pose final
+    fn ray_distance(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> Option<f32> {
+        // Esfera (la del cuerpo o la que envuelve a la malla)
+        let offset = origin - self.end;
+        let b = offset.dot(direction);
+        let c = offset.magnitude_squared() - self.radius * self.radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 || (c > 0.0 && b > 0.0) {
+            return None;
+        }
+        let sphere_distance = (-b - discriminant.sqrt()).max(0.0);
+        if sphere_distance > max_distance {
+            return None;
+        }
+
+        match &self.mesh {
+            Some(mesh) => {
+                // Las distancias en espacio de objeto se miden en unidades de la malla, no del mundo
+                let local_direction = mesh.vector_to_local(direction);
+                let local_per_world = local_direction.magnitude();
+                mesh.bvh
+                    .ray_cast(&mesh.point_to_local(origin), &(local_direction / local_per_world), max_distance * local_per_world)
+                    .map(|hit| hit.distance / local_per_world)
+            }
+            None => Some(sphere_distance),
+        }
+    }
+}
+
+struct Contact {
+    t: f32,
+    normal: Vec3,
+    position: Vec3,
+}
+
+fn safe_normalize(vector: Vec3) -> Vec3 {
+    if vector.magnitude_squared() > 0.0 { vector.normalize() } else { Vec3::new(0.0, 1.0, 0.0) }
+}
+
+// Choque de la nave con un cuerpo, para que el resto del programa pueda reaccionar (cámara, HUD...)
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionEvent {
+    pub body: usize,
+    pub normal: Vec3,      // Normal del contacto, del cuerpo hacia la nave
+    pub impact_speed: f32, // Velocidad relativa contra la superficie al momento del choque
+    pub bounced: bool,     // false si la nave solo deslizó
+}
+
+// Primer instante `t` en [0, 1] en que dos esferas en movimiento lineal se tocan. `offset` es la
+// posición relativa al inicio y `motion` el desplazamiento relativo durante el barrido.
+// Si ya se tocan al inicio devuelve 0.
+pub fn sweep_spheres(offset: Vec3, motion: Vec3, radius: f32) -> Option<f32> {
+    // |offset + motion * t| = radius  =>  a t^2 + 2 b t + c = 0
+    let c = offset.magnitude_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+
+    let a = motion.magnitude_squared();
+    let b = offset.dot(&motion);
+    if a == 0.0 || b >= 0.0 {
+        // Sin movimiento relativo, o alejándose
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let t = (-b - discriminant.sqrt()) / a;
+    (t <= 1.0).then_some(t)
+}
+
+// Mueve una esfera de radio `radius` desde `start` con `velocity` durante `dt` segundos contra
+// los cuerpos en movimiento. Al chocar la deja en el punto de contacto, quita (o invierte, si
+// rebota) la velocidad contra la superficie y sigue deslizando con el tiempo restante.
+// Devuelve la posición final y los choques del paso.
+pub fn move_and_collide(
+    start: Vec3,
+    velocity: &mut Vec3,
+    radius: f32,
+    dt: f32,
+    bodies: &[BodySweep],
+) -> (Vec3, Vec<CollisionEvent>) {
+    let mut position = start;
+    let mut events = Vec::new();
+    let mut elapsed = 0.0; // Fracción del paso ya recorrida
+
+    for _ in 0..MAX_ITERATIONS {
+        let remaining = 1.0 - elapsed;
+        if remaining <= 0.0 {
+            break;
+        }
+        let motion = *velocity * (dt * remaining);
+
+        // Buscar el primer contacto entre todos los cuerpos en lo que queda del paso
+        let mut first_hit: Option<(Contact, &BodySweep)> = None;
+        for body in bodies {
+            if let Some(contact) = body.first_contact(position, motion, radius, elapsed) {
+                if first_hit.as_ref().is_none_or(|(best, _)| contact.t < best.t) {
+                    first_hit = Some((contact, body));
+                }
+            }
+        }
+
+        let (contact, body) = match first_hit {
+            Some(hit) => hit,
+            None => {
+                position += motion;
+                break;
+            }
+        };
+
+        // Avanzar hasta el contacto, ya separado de la superficie
+        elapsed += remaining * contact.t;
+        position = contact.position;
+        let normal = contact.normal;
+
+        // Respuesta con la velocidad relativa al cuerpo (un planeta en órbita empuja a la nave)
+        let body_velocity = if dt != 0.0 { (body.end - body.start) / dt } else { Vec3::zeros() };
+        let relative = *velocity - body_velocity;
+        let normal_speed = relative.dot(&normal);
+        if normal_speed < 0.0 {
+            let bounced = -normal_speed > BOUNCE_MIN_SPEED;
+            let restitution = if bounced { RESTITUTION } else { 0.0 };
+            *velocity -= normal * (normal_speed * (1.0 + restitution));
+            events.push(CollisionEvent {
+                body: body.body,
+                normal,
+                impact_speed: -normal_speed,
+                bounced,
+            });
+        }
+    }
+
+    (position, events)
+}
+
+// Primer cuerpo que toca un rayo desde `origin` en la dirección `direction` (normalizada), con la distancia
+pub fn ray_cast(bodies: &[BodySweep], origin: &Vec3, direction: &Vec3, max_distance: f32) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+    for body in bodies {
+        let limit = best.map_or(max_distance, |(_, distance)| distance);
+        if let Some(distance) = body.ray_distance(origin, direction, limit) {
+            best = Some((body.body, distance));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Esfera quieta de radio 1 en el origen
+    fn still_sphere() -> BodySweep<'static> {
+        BodySweep { body: 7, start: Vec3::zeros(), end: Vec3::zeros(), radius: 1.0, mesh: None }
+    }
+
+    #[test]
+    fn sweep_starting_in_overlap_hits_at_zero() {
+        assert_eq!(sweep_spheres(Vec3::new(0.5, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0), 1.0), Some(0.0));
+        // Aunque se esté alejando
+        assert_eq!(sweep_spheres(Vec3::new(0.5, 0.0, 0.0), Vec3::new(-10.0, 0.0, 0.0), 1.0), Some(0.0));
+    }
+
+    #[test]
+    fn sweep_tangential_graze() {
+        // Pasa justo a la distancia del radio: se tocan en la mitad del barrido
+        let t = sweep_spheres(Vec3::new(1.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 10.0), 1.0).unwrap();
+        assert!((t - 0.5).abs() < 1e-3);
+        // Un poco más afuera no se tocan
+        assert!(sweep_spheres(Vec3::new(1.001, 0.0, -5.0), Vec3::new(0.0, 0.0, 10.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn sweep_misses_when_short_or_moving_away() {
+        assert!(sweep_spheres(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0), 1.0).is_none());
+        assert!(sweep_spheres(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(-3.0, 0.0, 0.0), 1.0).is_none());
+        assert!(sweep_spheres(Vec3::new(-5.0, 0.0, 0.0), Vec3::zeros(), 1.0).is_none());
+    }
+
+    #[test]
+    fn fast_head_on_impact_bounces() {
+        let mut velocity = Vec3::new(10.0, 0.0, 0.0);
+        let (position, events) = move_and_collide(Vec3::new(-5.0, 0.0, 0.0), &mut velocity, 0.5, 1.0, &[still_sphere()]);
+
+        assert_eq!(events.len(), 1);
+        let event = events[0];
+        assert_eq!(event.body, 7);
+        assert!(event.bounced);
+        assert!((event.impact_speed - 10.0).abs() < 1e-4);
+        assert!((event.normal - Vec3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-4);
+        // Rebota con la fracción RESTITUTION de la velocidad y se aleja el resto del paso
+        assert!((velocity - Vec3::new(-10.0 * RESTITUTION, 0.0, 0.0)).magnitude() < 1e-4);
+        assert!(position.x < -1.5 && position.x > -5.0);
+    }
+
+    #[test]
+    fn slow_impact_slides_along_the_surface() {
+        let mut velocity = Vec3::new(0.3, -0.5, 0.0);
+        let (position, events) = move_and_collide(Vec3::new(0.0, 2.0, 0.0), &mut velocity, 0.5, 2.0, &[still_sphere()]);
+
+        assert_eq!(events.len(), 1);
+        let event = events[0];
+        assert!(!event.bounced);
+        assert!(event.impact_speed < BOUNCE_MIN_SPEED);
+        // Sin rebote: se pierde la velocidad contra la superficie y se conserva la tangencial
+        assert!(velocity.dot(&event.normal).abs() < 1e-4);
+        assert!(velocity.x > 0.0);
+        assert!(position.magnitude() >= 1.5);
+    }
+
+    #[test]
+    fn starting_inside_a_body_pushes_out() {
+        let mut velocity = Vec3::zeros();
+        let (position, _) = move_and_collide(Vec3::new(1.0, 0.0, 0.0), &mut velocity, 0.5, 1.0, &[still_sphere()]);
+        assert!(position.magnitude() >= 1.5);
+        assert!(position.x > 0.0 && position.y.abs() < 1e-6);
+    }
+}
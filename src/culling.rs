@@ -0,0 +1,94 @@
+// culling.rs
+// Descarte de geometría antes de rasterizar: caras traseras por triángulo y frustum por modelo.
+use nalgebra_glm::{Mat3, Mat4, Vec3, Vec4};
+use serde::Deserialize;
+use std::fmt;
+use crate::vertex::Vertex;
+
+// Configuración de descarte de caras por modelo (`cull = "back"` o `"none"` en la escena)
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CullMode {
+    Back, // Descartar caras traseras (mallas cerradas como las esferas)
+    None, // Doble cara (mallas abiertas o con winding inconsistente, como eye.obj)
+}
+
+// Determina si un triángulo en espacio de recorte mira en dirección contraria a la cámara.
+// Usa el determinante de (x, y, w), que tiene el mismo signo que el área en NDC y sigue siendo
+// válido para vértices detrás de la cámara, así que se puede aplicar antes del recorte.
+// El cargador de OBJ invierte el eje Y, por lo que las caras frontales quedan en sentido horario
+// en NDC (determinante negativo).
+pub fn is_back_facing(triangle: &[Vertex; 3]) -> bool {
+    let [a, b, c] = [&triangle[0].clip_position, &triangle[1].clip_position, &triangle[2].clip_position];
+    let determinant = Mat3::new(
+        a.x, a.y, a.w,
+        b.x, b.y, b.w,
+        c.x, c.y, c.w,
+    ).determinant();
+    determinant >= 0.0
+}
+
+// Radio de la esfera envolvente de una malla en espacio de objeto
+pub fn mesh_bounding_radius(vertex_array: &[Vertex]) -> f32 {
+    vertex_array
+        .iter()
+        .map(|vertex| vertex.position.magnitude())
+        .fold(0.0, f32::max)
+}
+
+// Los seis planos del frustum extraídos de la matriz proyección * vista (Gribb-Hartmann).
+// Cada plano está normalizado y su normal apunta hacia el interior.
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| Vec4::new(
+            view_projection[(i, 0)],
+            view_projection[(i, 1)],
+            view_projection[(i, 2)],
+            view_projection[(i, 3)],
+        );
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let mut planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+        for plane in planes.iter_mut() {
+            let length = Vec3::new(plane.x, plane.y, plane.z).magnitude();
+            *plane /= length;
+        }
+
+        Frustum { planes }
+    }
+
+    // La esfera es visible si no queda completamente fuera de ningún plano
+    pub fn intersects_sphere(&self, center: &Vec3, radius: f32) -> bool {
+        self.planes.iter().all(|plane| {
+            plane.x * center.x + plane.y * center.y + plane.z * center.z + plane.w >= -radius
+        })
+    }
+}
+
+// Contadores de descarte de un frame
+#[derive(Default, Clone, Copy, Debug)]
+pub struct CullStats {
+    pub models_total: usize,
+    pub models_culled: usize,
+    pub triangles_total: usize,
+    pub triangles_backface: usize,
+    pub triangles_clipped: usize, // Triángulos completamente fuera del frustum tras el recorte
+}
+
+impl fmt::Display for CullStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "modelos {}/{} descartados | triángulos {} traseros, {} fuera del frustum de {}",
+            self.models_culled,
+            self.models_total,
+            self.triangles_backface,
+            self.triangles_clipped,
+            self.triangles_total
+        )
+    }
+}
@@ -7,29 +7,27 @@ pub struct Fragment {
     pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
-    pub intensity: f32,
     pub vertex_position: Vec3,
     pub tex_coords: Vec2, // Añadido: Coordenadas de textura (UV)
+    pub world_position: Vec3, // Posición interpolada en espacio de mundo, para la iluminación
 }
 
-impl Fragment {
-    pub fn new(
-        position: Vec2,
-        color: Color,
-        depth: f32,
-        normal: Vec3,
-        intensity: f32,
-        vertex_position: Vec3,
-        tex_coords: Vec2,
-    ) -> Self {
-        Fragment {
-            position,
-            color,
-            depth,
-            normal,
-            intensity,
-            vertex_position,
-            tex_coords,
-        }
+// Receptor de los fragmentos que produce el rasterizador (`triangle` y `line`), que los sombrea y
+// escribe en el acto en lugar de acumularlos en un Vec<Fragment>.
+pub trait FragmentVisitor {
+    // Early-Z: se consulta antes de interpolar atributos y sombrear. Devolver false descarta el
+    // fragmento sin invocar al shader.
+    fn test_depth(&mut self, _x: usize, _y: usize, _depth: f32) -> bool {
+        true
     }
+
+    fn visit(&mut self, fragment: Fragment);
 }
+
+// Cualquier closure que reciba fragmentos sirve como visitante (sin early-Z)
+impl<F: FnMut(Fragment)> FragmentVisitor for F {
+    fn visit(&mut self, fragment: Fragment) {
+        self(fragment)
+    }
+}
+
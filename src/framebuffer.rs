@@ -1,4 +1,8 @@
 // framebuffer.rs
+use std::thread;
+
+// Muestras por píxel de las estelas de velocidad de los saltos
+const WARP_STREAK_SAMPLES: usize = 8;
 
 // Framebuffer para gestionar el buffer de píxeles
 pub struct Framebuffer {
@@ -49,14 +53,6 @@ impl Framebuffer {
         self.current_color = color;
     }
 
-    // Establece el color de emisión en el buffer de emisión
-    pub fn set_emission_color(&mut self, x: usize, y: usize, color: u32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            self.emission_buffer[index] = color;
-        }
-    }
-
     pub fn point_emission(&mut self, x: usize, y: usize, depth: f32) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
@@ -122,6 +118,85 @@ impl Framebuffer {
         (r << 16) | (g << 8) | b
     }
 
+    // Divide el framebuffer en tiles de `tile_size` x `tile_size` pixeles (menos en los bordes), en
+    // orden de filas. Cada tile toma prestados en exclusiva sus pedazos de fila de los tres buffers,
+    // así que varios hilos pueden escribir a la vez en tiles distintos.
+    pub fn tiles_mut(&mut self, tile_size: usize) -> Vec<FramebufferTile<'_>> {
+        let width = self.width;
+        let tiles_x = width.div_ceil(tile_size);
+        let mut tiles: Vec<FramebufferTile> = Vec::new();
+
+        let rows = self.buffer.chunks_mut(width)
+            .zip(self.zbuffer.chunks_mut(width))
+            .zip(self.emission_buffer.chunks_mut(width));
+        for (y, ((buffer, zbuffer), emission_buffer)) in rows.enumerate() {
+            // Al empezar una fila de tiles se crean sus tiles vacíos
+            if y % tile_size == 0 {
+                for tile_x in 0..tiles_x {
+                    let x0 = tile_x * tile_size;
+                    tiles.push(FramebufferTile {
+                        x0,
+                        y0: y,
+                        columns: tile_size.min(width - x0),
+                        buffer: Vec::with_capacity(tile_size),
+                        zbuffer: Vec::with_capacity(tile_size),
+                        emission_buffer: Vec::with_capacity(tile_size),
+                    });
+                }
+            }
+
+            // Reparte la fila entre los tiles de la fila de tiles actual
+            let first = tiles.len() - tiles_x;
+            let pieces = buffer.chunks_mut(tile_size)
+                .zip(zbuffer.chunks_mut(tile_size))
+                .zip(emission_buffer.chunks_mut(tile_size));
+            for (tile, ((buffer, zbuffer), emission_buffer)) in tiles[first..].iter_mut().zip(pieces) {
+                tile.buffer.push(buffer);
+                tile.zbuffer.push(zbuffer);
+                tile.emission_buffer.push(emission_buffer);
+            }
+        }
+
+        tiles
+    }
+
+    // Combina el buffer de emisión con el buffer principal (screen blending). Se llama una vez por frame.
+    pub fn blend_emission(&mut self) {
+        for (color, &emission) in self.buffer.iter_mut().zip(self.emission_buffer.iter()) {
+            if emission != 0 {
+                *color = blend_screen(*color, emission);
+            }
+        }
+    }
+
+    // Estelas de velocidad para los saltos: desenfoque radial hacia el centro de la pantalla. Cada píxel
+    // promedia `WARP_STREAK_SAMPLES` muestras entre él y el centro; `strength` es el largo de la estela
+    // como fracción de la distancia al centro (0 no hace nada). Las filas se reparten entre varios hilos.
+    pub fn apply_warp_streaks(&mut self, strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+
+        let source = self.buffer.clone();
+        let (width, height) = (self.width, self.height);
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let rows_per_worker = height.div_ceil(workers).max(1);
+
+        thread::scope(|scope| {
+            for (index, rows) in self.buffer.chunks_mut(width * rows_per_worker).enumerate() {
+                let source = &source;
+                scope.spawn(move || {
+                    for (row, line) in rows.chunks_mut(width).enumerate() {
+                        let y = index * rows_per_worker + row;
+                        for (x, color) in line.iter_mut().enumerate() {
+                            *color = warp_streak_pixel(source, width, height, x, y, strength);
+                        }
+                    }
+                });
+            }
+        });
+    }
+
     /// Dibuja una línea entre dos puntos (x0, y0) y (x1, y1) usando el algoritmo de Bresenham.
     pub fn draw_line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, color: u32) {
         let mut x0 = x0 as i32;
@@ -152,5 +227,81 @@ impl Framebuffer {
         }
     }
 }
-    
 
+// Rectángulo [x0, x0 + columns) x [y0, y0 + rows) del framebuffer con acceso exclusivo a sus
+// pedazos de fila en los tres buffers
+pub struct FramebufferTile<'a> {
+    pub x0: usize,
+    pub y0: usize,
+    pub columns: usize,
+    buffer: Vec<&'a mut [u32]>,
+    zbuffer: Vec<&'a mut [f32]>,
+    emission_buffer: Vec<&'a mut [u32]>,
+}
+
+impl<'a> FramebufferTile<'a> {
+    pub fn rows(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 && x < self.x0 + self.columns && y >= self.y0 && y < self.y0 + self.rows()
+    }
+
+    // Prueba de profundidad sin escribir, para descartar fragmentos antes de sombrearlos
+    pub fn depth_test(&self, x: usize, y: usize, depth: f32) -> bool {
+        self.contains(x, y) && self.zbuffer[y - self.y0][x - self.x0] > depth
+    }
+
+    // Igual que `Framebuffer::point` pero con coordenadas absolutas de pantalla. Si el fragmento
+    // pasa la prueba de profundidad también reemplaza la emisión del pixel (0 = sin emisión).
+    pub fn point(&mut self, x: usize, y: usize, depth: f32, color: u32, emission: u32) {
+        if self.contains(x, y) {
+            let (row, column) = (y - self.y0, x - self.x0);
+            if self.zbuffer[row][column] > depth {
+                self.buffer[row][column] = color;
+                self.zbuffer[row][column] = depth;
+                self.emission_buffer[row][column] = emission;
+            }
+        }
+    }
+}
+
+// Promedio de las muestras entre el píxel (x, y) y el centro de la pantalla
+fn warp_streak_pixel(source: &[u32], width: usize, height: usize, x: usize, y: usize, strength: f32) -> u32 {
+    let center_x = width as f32 * 0.5;
+    let center_y = height as f32 * 0.5;
+    let offset_x = x as f32 - center_x;
+    let offset_y = y as f32 - center_y;
+    let last = (WARP_STREAK_SAMPLES - 1) as f32;
+    let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
+
+    for sample in 0..WARP_STREAK_SAMPLES {
+        let factor = 1.0 - strength * sample as f32 / last;
+        let sample_x = ((center_x + offset_x * factor) as usize).min(width - 1);
+        let sample_y = ((center_y + offset_y * factor) as usize).min(height - 1);
+        let color = source[sample_y * width + sample_x];
+        r += (color >> 16) & 0xFF;
+        g += (color >> 8) & 0xFF;
+        b += color & 0xFF;
+    }
+
+    let samples = WARP_STREAK_SAMPLES as u32;
+    ((r / samples) << 16) | ((g / samples) << 8) | (b / samples)
+}
+
+fn blend_screen(base: u32, emission: u32) -> u32 {
+    let base_r = (base >> 16) & 0xFF;
+    let base_g = (base >> 8) & 0xFF;
+    let base_b = base & 0xFF;
+
+    let emission_r = (emission >> 16) & 0xFF;
+    let emission_g = (emission >> 8) & 0xFF;
+    let emission_b = emission & 0xFF;
+
+    let screen_r = 255 - (((255 - base_r) * (255 - emission_r)) / 255);
+    let screen_g = 255 - (((255 - base_g) * (255 - emission_g)) / 255);
+    let screen_b = 255 - (((255 - base_b) * (255 - emission_b)) / 255);
+
+    (screen_r << 16) | (screen_g << 8) | screen_b
+}
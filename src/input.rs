@@ -0,0 +1,472 @@
+// input.rs
+// Capa de acciones sobre el teclado: el programa pregunta por acciones con nombre ("pausa",
+// "frenar"...) en lugar de por teclas, y cada acción sabe si se acaba de presionar, si se mantiene
+// o si se acaba de soltar. Las teclas de cada acción se leen al iniciar de un archivo TOML, así que
+// se pueden cambiar sin recompilar; las acciones que el archivo no menciona usan las teclas por defecto.
+// El mismo archivo tiene la configuración del mouse en la sección `[mouse]`.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use minifb::{Key, KeyRepeat, Window};
+use serde::Deserialize;
+
+// Archivo de teclas que se carga al iniciar
+pub const DEFAULT_KEYBINDINGS_PATH: &str = "src/assets/keybindings.toml";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    // Cámara orbital
+    BirdEyeView,
+    CameraLeft, // Las flechas orbitan en el modo orbital y giran la vista en el vuelo libre
+    CameraRight,
+    CameraUp,
+    CameraDown,
+    ZoomIn,
+    ZoomOut,
+    LockOn,
+    // Vuelo libre de la cámara
+    ToggleFreeFly,
+    FlyForward,
+    FlyBackward,
+    FlyLeft,
+    FlyRight,
+    FlyUp,
+    FlyDown,
+    FlyRollLeft,
+    FlyRollRight,
+    // Nave
+    ThrustForward,
+    ThrustBackward,
+    ThrustLeft,
+    ThrustRight,
+    ThrustUp,
+    ThrustDown,
+    PitchUp,
+    PitchDown,
+    YawLeft,
+    YawRight,
+    RollLeft,
+    RollRight,
+    Brake,
+    ToggleChaseCamera,
+    ToggleShipGravity,
+    // Tiempo
+    TogglePause,
+    StepOnce,
+    TimeScale1,
+    TimeScale2,
+    TimeScale10,
+    TimeScale100,
+    ToggleReverse,
+    ToggleNBody,
+    // Render
+    ToggleInterpolation,
+    // Recorridos de cámara
+    RecordKeyframe,
+    PlayCameraPath,
+    SaveCameraPath,
+    ClearCameraPath,
+    // Saltos a los cuerpos: cada cuerpo elige uno con `warp_key` (1 a 9) en la escena
+    Warp1,
+    Warp2,
+    Warp3,
+    Warp4,
+    Warp5,
+    Warp6,
+    Warp7,
+    Warp8,
+    Warp9,
+}
+
+// Modo de la cámara en el que se usa cada acción
+#[derive(Clone, Copy, PartialEq)]
+enum ActionMode {
+    Always,
+    Orbit,
+    FreeFly,
+}
+
+impl Action {
+    fn mode(self) -> ActionMode {
+        match self {
+            Action::BirdEyeView | Action::ZoomIn | Action::ZoomOut => ActionMode::Orbit,
+            Action::FlyForward
+            | Action::FlyBackward
+            | Action::FlyLeft
+            | Action::FlyRight
+            | Action::FlyUp
+            | Action::FlyDown
+            | Action::FlyRollLeft
+            | Action::FlyRollRight => ActionMode::FreeFly,
+            _ => ActionMode::Always,
+        }
+    }
+
+    // Dos acciones pueden compartir tecla solo si nunca se usan a la vez (una en la cámara orbital
+    // y la otra en el vuelo libre)
+    fn can_share_key_with(self, other: Action) -> bool {
+        matches!(
+            (self.mode(), other.mode()),
+            (ActionMode::Orbit, ActionMode::FreeFly) | (ActionMode::FreeFly, ActionMode::Orbit)
+        )
+    }
+}
+
+// Acción de salto de cada `warp_key`, del 1 al 9
+pub const WARP_ACTIONS: [Action; 9] = [
+    Action::Warp1, Action::Warp2, Action::Warp3, Action::Warp4, Action::Warp5,
+    Action::Warp6, Action::Warp7, Action::Warp8, Action::Warp9,
+];
+
+// Teclas por defecto de cada acción
+const DEFAULT_BINDINGS: &[(Action, &[Key])] = &[
+    (Action::Quit, &[Key::Escape]),
+    (Action::BirdEyeView, &[Key::B]),
+    (Action::CameraLeft, &[Key::Left]),
+    (Action::CameraRight, &[Key::Right]),
+    (Action::CameraUp, &[Key::Up]),
+    (Action::CameraDown, &[Key::Down]),
+    (Action::ZoomIn, &[Key::S]),
+    (Action::ZoomOut, &[Key::W]),
+    (Action::LockOn, &[Key::Key0]),
+    (Action::ToggleFreeFly, &[Key::Tab]),
+    (Action::FlyForward, &[Key::W]),
+    (Action::FlyBackward, &[Key::S]),
+    (Action::FlyLeft, &[Key::A]),
+    (Action::FlyRight, &[Key::D]),
+    (Action::FlyUp, &[Key::PageUp]),
+    (Action::FlyDown, &[Key::PageDown]),
+    (Action::FlyRollLeft, &[Key::Z]),
+    (Action::FlyRollRight, &[Key::X]),
+    (Action::ThrustForward, &[Key::T]),
+    (Action::ThrustBackward, &[Key::G]),
+    (Action::ThrustLeft, &[Key::F]),
+    (Action::ThrustRight, &[Key::H]),
+    (Action::ThrustUp, &[Key::E]),
+    (Action::ThrustDown, &[Key::Q]),
+    (Action::PitchUp, &[Key::I]),
+    (Action::PitchDown, &[Key::K]),
+    (Action::YawLeft, &[Key::J]),
+    (Action::YawRight, &[Key::L]),
+    (Action::RollLeft, &[Key::U]),
+    (Action::RollRight, &[Key::O]),
+    (Action::Brake, &[Key::R]),
+    (Action::ToggleChaseCamera, &[Key::C]),
+    (Action::ToggleShipGravity, &[Key::V]),
+    (Action::TogglePause, &[Key::Space]),
+    (Action::StepOnce, &[Key::Period]),
+    (Action::TimeScale1, &[Key::F1]),
+    (Action::TimeScale2, &[Key::F2]),
+    (Action::TimeScale10, &[Key::F3]),
+    (Action::TimeScale100, &[Key::F4]),
+    (Action::ToggleReverse, &[Key::F5]),
+    (Action::ToggleNBody, &[Key::N]),
+    (Action::ToggleInterpolation, &[Key::P]),
+    (Action::RecordKeyframe, &[Key::Insert]),
+    (Action::PlayCameraPath, &[Key::Home]),
+    (Action::SaveCameraPath, &[Key::End]),
+    (Action::ClearCameraPath, &[Key::Delete]),
+    (Action::Warp1, &[Key::Key1]),
+    (Action::Warp2, &[Key::Key2]),
+    (Action::Warp3, &[Key::Key3]),
+    (Action::Warp4, &[Key::Key4]),
+    (Action::Warp5, &[Key::Key5]),
+    (Action::Warp6, &[Key::Key6]),
+    (Action::Warp7, &[Key::Key7]),
+    (Action::Warp8, &[Key::Key8]),
+    (Action::Warp9, &[Key::Key9]),
+];
+
+// Teclas que se pueden usar en el archivo, por el nombre de la variante de `minifb::Key`
+const KEYS: &[Key] = &[
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11,
+    Key::F12, Key::F13, Key::F14, Key::F15,
+    Key::Down, Key::Left, Key::Right, Key::Up,
+    Key::Apostrophe, Key::Backquote, Key::Backslash, Key::Comma, Key::Equal, Key::LeftBracket, Key::Minus,
+    Key::Period, Key::RightBracket, Key::Semicolon, Key::Slash,
+    Key::Backspace, Key::Delete, Key::End, Key::Enter, Key::Escape, Key::Home, Key::Insert, Key::Menu,
+    Key::PageDown, Key::PageUp, Key::Pause, Key::Space, Key::Tab,
+    Key::NumLock, Key::CapsLock, Key::ScrollLock,
+    Key::LeftShift, Key::RightShift, Key::LeftCtrl, Key::RightCtrl, Key::LeftAlt, Key::RightAlt,
+    Key::LeftSuper, Key::RightSuper,
+    Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4, Key::NumPad5, Key::NumPad6,
+    Key::NumPad7, Key::NumPad8, Key::NumPad9, Key::NumPadDot, Key::NumPadSlash, Key::NumPadAsterisk,
+    Key::NumPadMinus, Key::NumPadPlus, Key::NumPadEnter,
+];
+
+// Control de la cámara con el mouse (sección `[mouse]` del archivo de teclas)
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseSettings {
+    pub sensitivity: f32, // Radianes por píxel al arrastrar (el desplazamiento usa la misma escala a la distancia del centro)
+    pub zoom_speed: f32,  // Unidades por paso de la rueda
+    pub invert_x: bool,
+    pub invert_y: bool,
+    pub invert_scroll: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        MouseSettings {
+            sensitivity: 0.005,
+            zoom_speed: 2.0,
+            invert_x: false,
+            invert_y: false,
+            invert_scroll: false,
+        }
+    }
+}
+
+// Contenido del archivo: la sección del mouse y las teclas de cada acción
+#[derive(Deserialize)]
+struct KeybindingsFile {
+    #[serde(default)]
+    mouse: MouseSettings,
+    #[serde(flatten)]
+    bindings: HashMap<Action, Vec<String>>,
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    KEYS.iter().copied().find(|key| format!("{:?}", key) == name)
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, message: String },
+    UnknownKey { action: Action, key: String },
+    InvalidMouse { path: String, message: String },
+    KeyConflict { key: Key, first: Action, second: Action },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Io { path, source } => write!(f, "Failed to read keybindings {}: {}", path, source),
+            InputError::Parse { path, message } => write!(f, "Failed to parse keybindings {}: {}", path, message),
+            InputError::UnknownKey { action, key } => write!(f, "Invalid keybinding for {:?}: unknown key \"{}\"", action, key),
+            InputError::InvalidMouse { path, message } => write!(f, "Invalid [mouse] settings in {}: {}", path, message),
+            InputError::KeyConflict { key, first, second } => {
+                write!(f, "Invalid keybindings: key {:?} is bound to both {:?} and {:?}, which are used at the same time", key, first, second)
+            }
+        }
+    }
+}
+
+// Estado de una acción en el frame actual
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActionState {
+    Idle,
+    Pressed,  // Se presionó en este frame
+    Held,     // Ya estaba presionada en el frame anterior
+    Released, // Se soltó en este frame
+}
+
+pub struct Input {
+    pub mouse: MouseSettings,
+    bindings: HashMap<Action, Vec<Key>>,
+    down: HashSet<Action>,
+    previous: HashSet<Action>,
+    repeated: HashSet<Action>, // Presionadas en este frame o repetidas por mantener la tecla
+}
+
+impl Input {
+    // Solo las teclas por defecto
+    pub fn with_defaults() -> Self {
+        Input {
+            mouse: MouseSettings::default(),
+            bindings: DEFAULT_BINDINGS
+                .iter()
+                .map(|(action, keys)| (*action, keys.to_vec()))
+                .collect(),
+            down: HashSet::new(),
+            previous: HashSet::new(),
+            repeated: HashSet::new(),
+        }
+    }
+
+    // Teclas por defecto más las del archivo, que reemplazan a las de las acciones que menciona;
+    // si el archivo no existe se usan solo las teclas y el mouse por defecto
+    pub fn load(path: &str) -> Result<Self, InputError> {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Input::with_defaults()),
+            Err(source) => return Err(InputError::Io { path: path.to_string(), source }),
+        };
+        let file: KeybindingsFile = toml::from_str(&source).map_err(|error| InputError::Parse {
+            path: path.to_string(),
+            message: error.to_string(),
+        })?;
+
+        if file.mouse.sensitivity < 0.0 {
+            let message = format!("sensitivity must not be negative, got {}", file.mouse.sensitivity);
+            return Err(InputError::InvalidMouse { path: path.to_string(), message });
+        }
+        if file.mouse.zoom_speed < 0.0 {
+            let message = format!("zoom_speed must not be negative, got {}", file.mouse.zoom_speed);
+            return Err(InputError::InvalidMouse { path: path.to_string(), message });
+        }
+
+        let mut input = Input::with_defaults();
+        input.mouse = file.mouse;
+        for (action, names) in file.bindings {
+            let keys = names
+                .iter()
+                .map(|name| parse_key(name).ok_or_else(|| InputError::UnknownKey { action, key: name.clone() }))
+                .collect::<Result<Vec<Key>, InputError>>()?;
+            input.bindings.insert(action, keys);
+        }
+        input.check_conflicts()?;
+        Ok(input)
+    }
+
+    // Una tecla no puede activar dos acciones que se usan al mismo tiempo
+    fn check_conflicts(&self) -> Result<(), InputError> {
+        let mut bound: Vec<(Action, Key)> = self
+            .bindings
+            .iter()
+            .flat_map(|(action, keys)| keys.iter().map(move |key| (*action, *key)))
+            .collect();
+        bound.sort_by_key(|(action, _)| *action); // Para que el error sea siempre el mismo
+
+        for (index, (first, key)) in bound.iter().enumerate() {
+            for (second, other) in &bound[index + 1..] {
+                if key == other && first != second && !first.can_share_key_with(*second) {
+                    return Err(InputError::KeyConflict { key: *key, first: *first, second: *second });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lee el teclado; se llama una vez al inicio de cada frame
+    pub fn update(&mut self, window: &Window) {
+        let mut down = HashSet::new();
+        let mut repeated = HashSet::new();
+        for (action, keys) in &self.bindings {
+            if keys.iter().any(|key| window.is_key_down(*key)) {
+                down.insert(*action);
+            }
+            if keys.iter().any(|key| window.is_key_pressed(*key, KeyRepeat::Yes)) {
+                repeated.insert(*action);
+            }
+        }
+        self.advance(down, repeated);
+    }
+
+    // Pasa al siguiente frame con las acciones presionadas en él
+    fn advance(&mut self, down: HashSet<Action>, repeated: HashSet<Action>) {
+        self.previous = std::mem::replace(&mut self.down, down);
+        self.repeated = repeated;
+    }
+
+    pub fn state(&self, action: Action) -> ActionState {
+        match (self.down.contains(&action), self.previous.contains(&action)) {
+            (true, false) => ActionState::Pressed,
+            (true, true) => ActionState::Held,
+            (false, true) => ActionState::Released,
+            (false, false) => ActionState::Idle,
+        }
+    }
+
+    // Se acaba de presionar (una sola vez aunque se mantenga la tecla)
+    pub fn pressed(&self, action: Action) -> bool {
+        self.state(action) == ActionState::Pressed
+    }
+
+    // Se acaba de soltar (por ahora solo lo usan las pruebas)
+    #[cfg(test)]
+    pub fn released(&self, action: Action) -> bool {
+        self.state(action) == ActionState::Released
+    }
+
+    // Está presionada, desde este frame o desde antes
+    pub fn held(&self, action: Action) -> bool {
+        self.down.contains(&action)
+    }
+
+    // Como `pressed`, pero se repite mientras se mantiene la tecla (con la demora del sistema)
+    pub fn repeated(&self, action: Action) -> bool {
+        self.repeated.contains(&action)
+    }
+
+    // Eje de -1 a 1 a partir de dos acciones opuestas
+    pub fn axis(&self, positive: Action, negative: Action) -> f32 {
+        (self.held(positive) as i32 - self.held(negative) as i32) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(input: &mut Input, down: &[Action]) {
+        let down: HashSet<Action> = down.iter().copied().collect();
+        input.advance(down.clone(), down);
+    }
+
+    #[test]
+    fn action_goes_from_pressed_to_held_to_released_to_idle() {
+        let mut input = Input::with_defaults();
+        assert_eq!(input.state(Action::Brake), ActionState::Idle);
+
+        frame(&mut input, &[Action::Brake]);
+        assert_eq!(input.state(Action::Brake), ActionState::Pressed);
+        assert!(input.pressed(Action::Brake) && input.held(Action::Brake));
+
+        frame(&mut input, &[Action::Brake]);
+        assert_eq!(input.state(Action::Brake), ActionState::Held);
+        assert!(!input.pressed(Action::Brake) && input.held(Action::Brake));
+
+        frame(&mut input, &[]);
+        assert_eq!(input.state(Action::Brake), ActionState::Released);
+        assert!(input.released(Action::Brake) && !input.held(Action::Brake));
+
+        frame(&mut input, &[]);
+        assert_eq!(input.state(Action::Brake), ActionState::Idle);
+        assert!(!input.released(Action::Brake));
+    }
+
+    #[test]
+    fn tapping_again_presses_again() {
+        let mut input = Input::with_defaults();
+        frame(&mut input, &[Action::TogglePause]);
+        frame(&mut input, &[]);
+        frame(&mut input, &[Action::TogglePause]);
+        assert!(input.pressed(Action::TogglePause));
+    }
+
+    #[test]
+    fn default_bindings_have_no_conflicts() {
+        assert!(Input::with_defaults().check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn one_key_cannot_drive_two_actions_at_once() {
+        // X ya alabea en el vuelo libre, pero frenar se usa en cualquier modo
+        let mut input = Input::with_defaults();
+        input.bindings.insert(Action::Brake, vec![Key::X]);
+        assert!(matches!(
+            input.check_conflicts(),
+            Err(InputError::KeyConflict { key: Key::X, first: Action::FlyRollRight, second: Action::Brake })
+        ));
+
+        // W sí puede alejar en la cámara orbital y avanzar en el vuelo libre
+        let mut input = Input::with_defaults();
+        input.bindings.insert(Action::ZoomIn, vec![Key::W]);
+        input.bindings.insert(Action::ZoomOut, vec![Key::S]);
+        assert!(input.check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn missing_file_uses_the_default_bindings() {
+        let input = Input::load("no/existe/keybindings.toml").unwrap();
+        assert_eq!(input.bindings[&Action::Warp6], vec![Key::Key6]);
+        assert_eq!(input.bindings.len(), DEFAULT_BINDINGS.len());
+    }
+}
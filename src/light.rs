@@ -0,0 +1,81 @@
+// light.rs
+// Luces de la escena. Todas las posiciones y direcciones están en espacio de mundo.
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    // Luz infinitamente lejana; `direction` es hacia dónde viaja la luz
+    Directional { direction: Vec3 },
+    // Luz que emite desde un punto en todas las direcciones (la estrella central del sistema).
+    // No se atenúa con la distancia para que los planetas exteriores sigan siendo visibles.
+    Point { position: Vec3 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Directional { direction: direction.normalize() },
+            color,
+            intensity,
+        }
+    }
+
+    pub fn point(position: Vec3, color: Color, intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Point { position },
+            color,
+            intensity,
+        }
+    }
+
+    // Dirección normalizada desde `point` hacia la luz
+    pub fn direction_to(&self, point: &Vec3) -> Vec3 {
+        match self.kind {
+            LightKind::Directional { direction } => -direction,
+            LightKind::Point { position } => {
+                let to_light = position - point;
+                if to_light.magnitude() > 0.0 {
+                    to_light.normalize()
+                } else {
+                    Vec3::new(0.0, 1.0, 0.0)
+                }
+            }
+        }
+    }
+}
+
+// Luz difusa total que recibe un punto: la suma de lambert por la intensidad de cada luz, sin color
+pub fn diffuse(lights: &[Light], normal: &Vec3, position: &Vec3) -> f32 {
+    lights
+        .iter()
+        .map(|light| normal.dot(&light.direction_to(position)).max(0.0) * light.intensity)
+        .sum()
+}
+
+// Aplica `color * (ambient + diffuse * lambert)` por canal, teñido con el color de cada luz
+pub fn illuminate(color: Color, ambient: f32, diffuse: f32, lights: &[Light], normal: &Vec3, position: &Vec3) -> Color {
+    let mut r = ambient;
+    let mut g = ambient;
+    let mut b = ambient;
+
+    for light in lights {
+        let lambert = normal.dot(&light.direction_to(position)).max(0.0) * light.intensity * diffuse;
+        r += lambert * light.color.r as f32 / 255.0;
+        g += lambert * light.color.g as f32 / 255.0;
+        b += lambert * light.color.b as f32 / 255.0;
+    }
+
+    Color::new(
+        (color.r as f32 * r).clamp(0.0, 255.0) as u8,
+        (color.g as f32 * g).clamp(0.0, 255.0) as u8,
+        (color.b as f32 * b).clamp(0.0, 255.0) as u8,
+    )
+}
@@ -1,11 +1,10 @@
-use crate::fragment::Fragment;
+use crate::fragment::{Fragment, FragmentVisitor};
 use crate::vertex::Vertex;
 use crate::color::Color;
 use nalgebra_glm::{Vec3, Vec2}; // Importamos Vec3 para las normales
 
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
-
+// Rasteriza la línea con Bresenham y entrega cada fragmento a `visitor`
+pub fn line(a: &Vertex, b: &Vertex, visitor: &mut impl FragmentVisitor) {
     let start = a.transformed_position;
     let end = b.transformed_position;
 
@@ -30,21 +29,25 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
         };
 
         let z = start.z + (end.z - start.z) * t;
-        let tex_coords = a.tex_coords + (b.tex_coords - a.tex_coords) * t;
 
-        // Normal y color predeterminados
-        let default_normal = Vec3::new(0.0, 0.0, 1.0);
-        let intensity = 1.0;
+        // Early-Z: solo se construye el fragmento si pasa la prueba de profundidad
+        if visitor.test_depth(x0 as usize, y0 as usize, z) {
+            let tex_coords = a.tex_coords + (b.tex_coords - a.tex_coords) * t;
+            let world_position = a.world_position + (b.world_position - a.world_position) * t;
+
+            // Normal y color predeterminados
+            let default_normal = Vec3::new(0.0, 0.0, 1.0);
 
-        fragments.push(Fragment::new(
-            Vec2::new(x0 as f32, y0 as f32),        // position
-            Color::new(255, 255, 255),             // color
-            z,                                     // depth
-            default_normal,                        // normal
-            intensity,                             // intensity
-            Vec3::new(x0 as f32, y0 as f32, z),    // vertex_position (en 3D)
-            tex_coords,                            // tex_coords
-        ));
+            visitor.visit(Fragment {
+                position: Vec2::new(x0 as f32, y0 as f32),
+                color: Color::new(255, 255, 255),
+                depth: z,
+                normal: default_normal,
+                vertex_position: Vec3::new(x0 as f32, y0 as f32, z), // En 3D
+                tex_coords,
+                world_position,
+            });
+        }
 
         if x0 == x1 && y0 == y1 {
             break;
@@ -60,6 +63,4 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             y0 += sy;
         }
     }
-
-    fragments
 }
@@ -1,5 +1,5 @@
-use nalgebra_glm::{look_at, perspective, Vec3, Mat4, Mat3, Vec4};
-use minifb::{Key, Window, WindowOptions};
+use nalgebra_glm::{look_at, perspective, Vec3, Mat4, Mat3, Vec4, Quat, quat_angle_axis, quat_to_mat4};
+use minifb::{MouseButton, MouseMode, Window, WindowOptions};
 use std::time::Duration;
 use std::f32::consts::PI;
 
@@ -17,6 +17,22 @@ mod texture;
 mod normal_map;
 mod skybox;
 mod audio_player;
+mod clipping;
+mod culling;
+mod tile_rasterizer;
+mod light;
+mod noise_cache;
+mod material;
+mod scene;
+mod orbit;
+mod clock;
+mod nbody;
+mod spaceship;
+mod collision;
+mod bvh;
+mod warp;
+mod input;
+mod camera_path;
 
 use audio_player::AudioPlayer;
 use skybox::Skybox;
@@ -24,18 +40,24 @@ use normal_map::{init_normal_map, with_normal_map};
 use texture::{init_texture, with_texture};
 use framebuffer::Framebuffer;
 use vertex::Vertex;
-use obj::Obj;
-use triangle::triangle;
-use shader::{vertex_shader, pattern_fragment_shader};  
-use planets_shader::{rocky_planet_shader, 
-                    gaseous_planet_shader, 
-                    frozen_planet_shader, 
-                    earth_planet_shader, 
-                    oceanic_planet_shader,
-                    ufo_shader,
-                    gargantua_shader,
-                    wormhole_shader};  
-use camera::Camera;
+use triangle::InterpolationMode;
+use tile_rasterizer::{rasterize_tiles, ScreenTriangle};
+use light::Light;
+use material::{Material, MaterialRegistry};
+use orbit::KeplerOrbit;
+use clock::{SimulationClock, FIXED_TIMESTEP};
+use nbody::{NBodySystem, GRAVITATIONAL_CONSTANT};
+use spaceship::Spaceship;
+use collision::{ray_cast, BodySweep, CollisionEvent, MeshCollider};
+use bvh::Bvh;
+use warp::Warp;
+use input::{Action, Input, MouseSettings, DEFAULT_KEYBINDINGS_PATH};
+use camera_path::{CameraPath, DEFAULT_CAMERA_PATH};
+use scene::{Scene, WarpDescription, DEFAULT_SCENE_PATH};
+use clipping::{clip_triangle, Clipped, MAX_CLIPPED_VERTICES};
+use culling::{CullMode, CullStats, Frustum, is_back_facing};
+use shader::{vertex_shader, viewport_transform, pattern_fragment_shader};  
+use camera::{Camera, CameraMode};
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 
 pub struct Uniforms<'a> {
@@ -45,71 +67,68 @@ pub struct Uniforms<'a> {
     viewport_matrix: Mat4,
     transformation_matrix: Mat4,
     normal_matrix: Mat3,
-    time: u32,
+    time: f32, // Tiempo de simulación en segundos
     noise: &'a FastNoiseLite,
     emission_intensity: f32,
+    interpolation_mode: InterpolationMode,
+    lights: &'a [Light],
 }
 
 
 struct Model<'a> {
     vertex_array: &'a [Vertex],
-    shader: PlanetShader,
+    material: &'a dyn Material,
+    parent: Option<usize>, // Índice del modelo padre; `position` y `rotation` son relativas a él
     position: Vec3,
     scale: f32,
-    rotation: Vec3,
-    rotation_speed: Vec3,
+    rotation: Quat, // Orientación relativa al padre (la heredan los hijos)
+    rotation_speed: Vec3, // Velocidad de giro sobre su propio eje en radianes por segundo
+    spin: Vec3, // Giro acumulado sobre su propio eje (no lo heredan los hijos)
     collision_radius: f32, // Radio de colisión
-    orbit_angle: f32, // Nuevo campo para almacenar el ángulo de órbita
-    orbit_speed: f32, // Nuevo campo para la velocidad de órbita
-    orbit_radius: f32, // Nuevo campo para almacenar el radio de la órbita
+    collision_mesh: Option<&'a Bvh>, // Si está, se choca contra los triángulos de la malla en lugar de la esfera
+    mass: f32, // Masa para el modo de gravedad N-cuerpos
+    orbit: Option<KeplerOrbit>, // Órbita alrededor del padre (o del origen)
+    cull_mode: CullMode, // Descarte de caras traseras (None para mallas de doble cara)
+    mesh_radius: f32, // Radio de la esfera envolvente de la malla en espacio de objeto
+    warp_key: Option<Action>, // Acción de salto (warp1 a warp9) que lleva a este cuerpo
+    on_spaceship: bool, // Cuelga de la nave: viaja con ella, así que no choca con ella ni entra en N-cuerpos
+    frame: Mat4, // Traslación * rotación en espacio de mundo, sin escala (la heredan los hijos)
+    world_matrix: Mat4, // Matriz de modelo final: `frame` * escala
+    world_position: Vec3,
 }
 
-#[derive(PartialEq)]
-enum PlanetShader {
-    Rocky,
-    Gaseous,
-    Frozen,
-    Earth,
-    Oceanic,
-    Ufo,
-    Gargantua,
-    Wormhole,
-}
+// Segundos que el último choque de la nave se muestra en el título
+const COLLISION_MESSAGE_TIME: f32 = 2.0;
 
+// Desplazamiento de la cámara de persecución por unidad de velocidad de impacto
+const COLLISION_JOLT: f32 = 0.05;
 
-enum CurrentModel {
-    Sphere,
-    Ufo,
-    Eye,
-}
+// Distancia máxima a la que se busca el cuerpo que la nave tiene enfrente
+const TARGET_RANGE: f32 = 200.0;
 
+// Distancia mínima al centro al acercarse con la rueda del mouse
+const MIN_ZOOM_DISTANCE: f32 = 0.5;
 
 fn render_orbits(framebuffer: &mut Framebuffer, models: &[Model], view_matrix: &Mat4, projection_matrix: &Mat4) {
-    let orbit_points = 100; // Número de segmentos para aproximar el círculo de la órbita
-    let center = Vec3::new(0.0, 0.0, 0.0); // Centro del sistema de órbitas (origen)
+    let orbit_points = 100; // Número de segmentos para aproximar la elipse de la órbita
 
     for model in models {
-        // Excluir los modelos que no deben tener órbita
-        if model.shader == PlanetShader::Ufo || model.shader == PlanetShader::Wormhole {
-            continue;
-        }
+        // Excluir los modelos que no tienen órbita
+        let orbit = match &model.orbit {
+            Some(orbit) => orbit,
+            None => continue,
+        };
 
-        // Usar el radio fijo de la órbita
-        let orbit_radius = model.orbit_radius;
+        // La órbita se dibuja en el marco del padre (el origen si no tiene)
+        let parent_frame = model.parent.map_or(Mat4::identity(), |parent| models[parent].frame);
 
-        // Generar puntos de la órbita
+        // Generar puntos de la elipse
         let mut orbit_vertices = Vec::with_capacity(orbit_points);
-        for i in 0..orbit_points {
-            let angle = 2.0 * PI * (i as f32 / orbit_points as f32);
-            let x = center.x + orbit_radius * angle.cos();
-            let z = center.z + orbit_radius * angle.sin();
-            let y = center.y;
-
-            let orbit_point = Vec3::new(x, y, z);
+        for point in orbit.points(orbit_points) {
+            let orbit_point = Vec4::new(point.x, point.y, point.z, 1.0);
 
-            // Transformar el punto de la órbita usando las matrices de vista y proyección
-            let model_position = nalgebra_glm::translation(&orbit_point);
-            let clip_position = projection_matrix * view_matrix * model_position * Vec4::new(1.0, 1.0, 1.0, 1.0);
+            // Transformar el punto de la órbita usando las matrices del padre, de vista y de proyección
+            let clip_position = projection_matrix * view_matrix * parent_frame * orbit_point;
 
             orbit_vertices.push(clip_position);
         }
@@ -145,60 +164,6 @@ fn render_orbits(framebuffer: &mut Framebuffer, models: &[Model], view_matrix: &
     }
 }
 
-fn check_collision(model_a: &Model, model_b: &Model) -> bool {
-    let distance = nalgebra_glm::distance(&model_a.position, &model_b.position);
-    let combined_radius = model_a.collision_radius + model_b.collision_radius;
-    distance < combined_radius
-}
-
-// Función para mover la nave a una posición cerca del planeta seleccionado
-fn warp_to_planet(camera: &mut Camera, planet: &Model) {
-    let warp_distance = 15.0; // Distancia fija para ubicarse frente al planeta
-
-    // Calcular el vector de dirección desde el planeta hacia la cámara
-    let direction = nalgebra_glm::normalize(&(camera.eye - planet.position));
-
-    // Actualizar la posición de la cámara: frente al planeta, en la dirección opuesta
-    camera.eye = planet.position + direction * warp_distance;
-
-    // Apuntar la cámara hacia el planeta
-    camera.center = planet.position;
-
-    // Ajustar los ángulos de rotación
-    let forward = camera.get_forward_vector();
-    camera.pitch = forward.y.atan2((forward.x.powi(2) + forward.z.powi(2)).sqrt());
-    camera.yaw = forward.z.atan2(forward.x);
-
-    // Indicar que la cámara ha cambiado
-    camera.has_changed = true;
-}
-
-fn blend_screen(base: u32, emission: u32) -> u32 {
-    let base_r = (base >> 16) & 0xFF;
-    let base_g = (base >> 8) & 0xFF;
-    let base_b = base & 0xFF;
-
-    let emission_r = (emission >> 16) & 0xFF;
-    let emission_g = (emission >> 8) & 0xFF;
-    let emission_b = emission & 0xFF;
-
-    let screen_r = 255 - (((255 - base_r) * (255 - emission_r)) / 255);
-    let screen_g = 255 - (((255 - base_g) * (255 - emission_g)) / 255);
-    let screen_b = 255 - (((255 - base_b) * (255 - emission_b)) / 255);
-
-    (screen_r << 16) | (screen_g << 8) | screen_b
-}
-
-// Inicializa la cámara en función del modelo seleccionado
-fn initialize_camera(model: &CurrentModel) -> Camera {
-    match model {
-        CurrentModel::Sphere => Camera::new(Vec3::new(0.0, 20.0, 30.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
-        CurrentModel::Ufo => Camera::new(Vec3::new(0.0, 0.0, 35.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
-        CurrentModel::Eye => Camera::new(Vec3::new(0.0, 0.0, 15.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
-
-    }
-}
-
 fn create_cracked_earth_noise() -> FastNoiseLite {
     let mut noise = FastNoiseLite::with_seed(42);
     noise.set_noise_type(Some(NoiseType::Cellular));
@@ -221,6 +186,38 @@ fn create_model_matrix_with_rotation(position: Vec3, scale: f32, rotation: Vec3)
     translation * rotation_matrix * scaling
 }
 
+// Marco en espacio de mundo del modelo `index`: su traslación y rotación compuestas con las de
+// todos sus ancestros. La escala no se hereda, así que los hijos se ubican en unidades de mundo.
+fn resolve_frame(models: &[Model], frames: &mut [Option<Mat4>], index: usize) -> Mat4 {
+    if let Some(frame) = frames[index] {
+        return frame;
+    }
+
+    let model = &models[index];
+    let local = nalgebra_glm::translation(&model.position) * quat_to_mat4(&model.rotation);
+    let frame = match model.parent {
+        Some(parent) => resolve_frame(models, frames, parent) * local,
+        None => local,
+    };
+    frames[index] = Some(frame);
+    frame
+}
+
+// Recorre la jerarquía y actualiza las matrices y posiciones de mundo de todos los modelos
+fn update_world_transforms(models: &mut [Model]) {
+    let mut frames = vec![None; models.len()];
+    for index in 0..models.len() {
+        resolve_frame(models, &mut frames, index);
+    }
+
+    for (model, frame) in models.iter_mut().zip(frames) {
+        let frame = frame.unwrap();
+        model.frame = frame;
+        model.world_matrix = frame * create_model_matrix_with_rotation(Vec3::zeros(), model.scale, model.spin);
+        model.world_position = Vec3::new(frame[(0, 3)], frame[(1, 3)], frame[(2, 3)]);
+    }
+}
+
 fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     Mat4::new(
         width / 2.0, 0.0, 0.0, width / 2.0,
@@ -230,12 +227,14 @@ fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
     )
 }
 
-fn create_projection_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = PI / 4.0;
-    let aspect_ratio = window_width / window_height;
+// `fov` es el campo de visión vertical en radianes. `perspective` recibe primero el aspecto y luego
+// el fov; la escena siempre se ha visto con PI / 4 como aspecto (un poco estirada a lo ancho), así que
+// se conserva ese factor sobre el aspecto de la ventana.
+fn create_projection_matrix(window_width: f32, window_height: f32, fov: f32) -> Mat4 {
+    let aspect_ratio = window_width / window_height * (PI / 4.0);
     let near = 0.1;
     let far = 100.0;
-    perspective(fov, aspect_ratio, near, far)
+    perspective(aspect_ratio, fov, near, far)
 }
 
 fn create_model_matrix(position: Vec3, scale: f32) -> Mat4 {
@@ -244,18 +243,24 @@ fn create_model_matrix(position: Vec3, scale: f32) -> Mat4 {
     translation_matrix * scale_matrix
 }
 
-fn generate_spiral_position(index: usize, base_radius: f32, height_step: f32) -> Vec3 {
-    let angle = index as f32 * 0.8 * PI; // Aumentamos el ángulo para cubrir más espacio
-    let radius = base_radius + index as f32 * 10.0; // Incremento del radio aumentado a 10.0
-    let x = radius * angle.cos();
-    let z = radius * angle.sin();
-    let y = (height_step * index as f32 * 2.0) - 10.0; // Ajuste de altura para mayor separación
-
-    Vec3::new(x, y, z)
+// Los errores de los archivos (escena, teclas, recorrido) se muestran tal cual para que apunten a la
+// entrada culpable
+fn exit_with_error(error: impl std::fmt::Display) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
 }
 
-fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Vertex], planet_shader: &PlanetShader) {
-    
+// Etapas de geometría de un modelo: vertex shader, ensamblado, descarte y recorte. Los triángulos
+// resultantes (en pantalla) se agregan a `screen_triangles` etiquetados con la llamada de dibujo `draw`;
+// la rasterización y el sombreado se hacen después para todo el frame en `rasterize_tiles`.
+fn render(
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    cull_mode: CullMode,
+    draw: usize,
+    screen_triangles: &mut Vec<ScreenTriangle>,
+    stats: &mut CullStats,
+) {
     // Vertex Shader Stage:
     // Transforma cada vértice utilizando las matrices de transformación y proyecta a coordenadas de la cámara.
     let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
@@ -265,68 +270,56 @@ fn render(framebuffer: &mut Framebuffer, uniforms: &Uniforms, vertex_array: &[Ve
     }
 
     // Primitive Assembly Stage:
-    // Agrupa los vértices transformados en triángulos. Cada grupo de tres vértices se convierte en un triángulo.
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push([
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ]);
+    // Agrupa los vértices transformados en triángulos. Cada grupo de tres vértices se convierte en un triángulo
+    // (los vértices se mueven fuera de la lista, sin copiarlos).
+    // Los triángulos que miran en dirección contraria a la cámara se descartan aquí según `cull_mode`.
+    //
+    // Clipping Stage:
+    // Recorta cada triángulo contra los planos del frustum en espacio homogéneo y re-triangula el
+    // polígono resultante en abanico. Después aplica la división de perspectiva y el viewport.
+    let mut polygon = Vec::with_capacity(MAX_CLIPPED_VERTICES);
+    let mut scratch = Vec::with_capacity(MAX_CLIPPED_VERTICES);
+    let mut vertices = transformed_vertices.into_iter();
+    while let (Some(a), Some(b), Some(c)) = (vertices.next(), vertices.next(), vertices.next()) {
+        let mut tri = [a, b, c];
+        stats.triangles_total += 1;
+        if cull_mode == CullMode::Back && is_back_facing(&tri) {
+            stats.triangles_backface += 1;
+            continue;
         }
-    }
-
-    // Rasterization Stage:
-    // Convierte los triángulos en fragmentos (píxeles de la pantalla) que serán procesados individualmente.
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle(&tri[0], &tri[1], &tri[2]));
-    }
-
-    // Aplica el shader seleccionado a cada fragmento para determinar el color final del pixel.
-    // Este shader se selecciona según el tipo de planeta u objeto (por ejemplo, `PlanetShader::Rocky` para un planeta rocoso).
-    // Fragment Processing Stage:
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        if x < framebuffer.width && y < framebuffer.height {
-            let (shaded_color, emission_color) = match planet_shader {
-                PlanetShader::Rocky => (rocky_planet_shader(&fragment, uniforms), None), //ya
-                PlanetShader::Gaseous => (gaseous_planet_shader(&fragment, uniforms), None), //ya
-                PlanetShader::Frozen => (frozen_planet_shader(&fragment, uniforms), None),
-                PlanetShader::Earth => (earth_planet_shader(&fragment, uniforms), None), //ya
-                PlanetShader::Oceanic => (oceanic_planet_shader(&fragment, uniforms), None), //ya
-                PlanetShader::Ufo => (ufo_shader(&fragment, uniforms), None), //ya
-                PlanetShader::Gargantua => gargantua_shader(&fragment, uniforms), //ya
-                PlanetShader::Wormhole => wormhole_shader(&fragment, uniforms), //ya
-            };
-            
-    
-            framebuffer.set_current_color(shaded_color.to_hex());
-            framebuffer.point(x, y, fragment.depth);
 
-            if let Some(emission) = emission_color {
-                framebuffer.set_emission_color(x, y, emission.to_hex());
+        match clip_triangle(&tri, &mut polygon, &mut scratch) {
+            Clipped::Inside => {
+                for vertex in tri.iter_mut() {
+                    viewport_transform(vertex, uniforms);
+                }
+                screen_triangles.push(ScreenTriangle { vertices: tri, draw });
             }
-            //Aplicar el post-procesamiento de emisión
-            // framebuffer.apply_emission();
-
-        }
-    }
-    
-    // Segunda pasada: Combinar el buffer de emisión en el buffer principal
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let emission_color = framebuffer.emission_buffer[y * framebuffer.width + x];
-            let current_color = framebuffer.buffer[y * framebuffer.width + x];
-
-            // Mezclamos los colores con el buffer de emisión usando un modo de mezcla (screen blending)
-            if emission_color != 0 {
-                framebuffer.buffer[y * framebuffer.width + x] = blend_screen(current_color, emission_color);
+            Clipped::Polygon => {
+                for vertex in polygon.iter_mut() {
+                    viewport_transform(vertex, uniforms);
+                }
+                // Los triángulos del abanico comparten vértices, así que aquí sí se copian
+                for i in 1..polygon.len() - 1 {
+                    screen_triangles.push(ScreenTriangle {
+                        vertices: [
+                            polygon[0].clone(),
+                            polygon[i].clone(),
+                            polygon[i + 1].clone(),
+                        ],
+                        draw,
+                    });
+                }
             }
+            Clipped::Outside => stats.triangles_clipped += 1,
         }
-    }   
+    }
+}
+
+// Uniforms y material de un modelo visible en el frame actual
+struct DrawCall<'a> {
+    uniforms: Uniforms<'a>,
+    material: &'a dyn Material,
 }
 
 fn main() {
@@ -352,13 +345,17 @@ fn main() {
     audio_player.play();
 
     let noise = create_cracked_earth_noise();
+    let materials = MaterialRegistry::with_builtin();
     
-    let sphere_obj = Obj::load("src/assets/sphere.obj").expect("Failed to load sphere.obj");
-    let ufo_obj = Obj::load("src/assets/ufo.obj").expect("Failed to load ufo.obj");
-    let eye_obj = Obj::load("src/assets/eye.obj").expect("Failed to load sphere.obj");
+    // Cargar la escena (se puede pasar otra como primer argumento)
+    let scene_path = std::env::args().nth(1).unwrap_or_else(|| DEFAULT_SCENE_PATH.to_string());
+    let scene = Scene::load(&scene_path).unwrap_or_else(|error| exit_with_error(error));
+
+    // Teclas de cada acción
+    let mut input = Input::load(DEFAULT_KEYBINDINGS_PATH).unwrap_or_else(|error| exit_with_error(error));
 
-    //Inicializar nave para explorar el espacio
-    let spaceship_obj = Obj::load("src/assets/spaceship.obj").expect("Failed to load spaceship.obj");
+    // Recorrido de cámara guardado (vacío si todavía no hay uno)
+    let mut camera_path = CameraPath::load(DEFAULT_CAMERA_PATH).unwrap_or_else(|error| exit_with_error(error));
 
     // Inicializar la textura
     init_texture("src/assets/textures/water.png").expect("Failed to initialize texture");
@@ -366,177 +363,239 @@ fn main() {
     // Inicializar el mapa normal
     init_normal_map("src/assets/textures/water.png").expect("Failed to load normal map");
 
-    let skybox = Skybox::new(900); // Genera 500 estrellas
-
-    // Almacenar los arrays de vértices en variables
-    let sphere_vertices = sphere_obj.get_vertex_array();
-    let ufo_vertices = ufo_obj.get_vertex_array();
-    let eye_vertices = eye_obj.get_vertex_array();
-    let spaceship_vertices = spaceship_obj.get_vertex_array();
-
-    let mut current_model = CurrentModel::Sphere; // Empezamos con el modelo esfera
-
-    // Inicializa la cámara en función del modelo actual
-    let mut camera = initialize_camera(&current_model);
-
-    // Crear la lista de modelos con las posiciones en espiral
-    let mut models = vec![
-        Model {
-            vertex_array: &eye_vertices,
-            shader: PlanetShader::Wormhole,
-            position: Vec3::new(0.0, 0.0, 0.0),
-            scale: 2.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.2, 0.0),
-            collision_radius: 3.0,
-            orbit_angle: 0.0,
-            orbit_speed: 0.0,
-            orbit_radius: 0.0,
-        },
-        Model {
-            vertex_array: &sphere_vertices,
-            shader: PlanetShader::Rocky,
-            position: generate_spiral_position(1, 5.0, 1.0),
-            scale: 1.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.4, 0.0),
-            collision_radius: 3.5,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(1, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &sphere_vertices,
-            shader: PlanetShader::Oceanic,
-            position: generate_spiral_position(2, 5.0, 1.0),
-            scale: 1.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.5, 0.0),
-            collision_radius: 2.5,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(2, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &sphere_vertices,
-            shader: PlanetShader::Earth,
-            position: generate_spiral_position(3, 5.0, 1.0),
-            scale: 1.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.8, 0.0),
-            collision_radius: 3.5,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(3, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &sphere_vertices,
-            shader: PlanetShader::Frozen,
-            position: generate_spiral_position(4, 5.0, 1.0),
-            scale: 1.5,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.13, 0.0),
-            collision_radius: 3.5,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(4, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &sphere_vertices,
-            shader: PlanetShader::Gaseous,
-            position: generate_spiral_position(5, 5.0, 1.0),
-            scale: 2.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.21, 0.0),
-            collision_radius: 4.0,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(5, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &ufo_vertices,
-            shader: PlanetShader::Ufo,
-            position: generate_spiral_position(6, 5.0, 1.0),
-            scale: 0.005,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.8, 0.0),
-            collision_radius: 0.05,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(6, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &eye_vertices,
-            shader: PlanetShader::Gargantua,
-            position: generate_spiral_position(7, 5.0, 1.0),
-            scale: 2.0,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.0, 0.0),
-            collision_radius: 4.0,
-            orbit_angle: 0.0,
-            orbit_speed: 0.01,
-            orbit_radius: nalgebra_glm::distance(&Vec3::new(0.0, 0.0, 0.0), &generate_spiral_position(7, 5.0, 1.0)),
-        },
-        Model {
-            vertex_array: &spaceship_vertices,
-            shader: PlanetShader::Ufo,
-            position: camera.eye + camera.get_forward_vector() * 4.0,
-            scale: 0.02,
-            rotation: Vec3::new(0.0, 0.0, 0.0),
-            rotation_speed: Vec3::new(0.0, 0.0, 0.0),
-            collision_radius: 8.0,
-            orbit_angle: 0.0,
-            orbit_speed: 0.0,
-            orbit_radius: 0.0,
-        },
-    ];
+    let skybox = Skybox::new(scene.description.skybox.star_count);
+
+    // Inicializa la cámara en la posición inicial de la escena
+    let mut camera = scene.camera();
+
+    // Crear la lista de modelos de la escena (la nave queda al final)
+    let mut models = scene.build_models(&materials).unwrap_or_else(|error| exit_with_error(error));
+    let scene_lights = scene.lights().unwrap_or_else(|error| exit_with_error(error));
+
+    // Los cuerpos que siguen a la cámara empiezan frente a ella
+    for model in models.iter_mut().filter(|model| model.material.follows_camera()) {
+        model.position = camera.eye + camera.get_forward_vector() * 4.0;
+    }
+
+    // La nave (el último modelo) vuela por su cuenta desde frente a la cámara
+    let mut spaceship = Spaceship::in_front_of(&camera);
+    spaceship.sync_model(models.last_mut().unwrap());
+    let mut last_collision: Option<(CollisionEvent, f32)> = None;
+    update_world_transforms(&mut models);
     
-    let mut time_counter = 0;
-    let mut current_planet_shader = PlanetShader::Rocky;
+    let mut clock = SimulationClock::new();
+
+    // Modo de gravedad N-cuerpos (N lo activa); mientras está apagado los cuerpos siguen sus órbitas
+    let mut gravity: Option<NBodySystem> = None;
+
+    // Salto animado en curso (teclas numéricas); mientras dura, la cámara y la nave vuelan solas
+    let mut warp: Option<Warp> = None;
+
+    // Posición del mouse en el frame anterior, para saber cuánto se arrastró
+    let mut last_mouse_position: Option<(f32, f32)> = None;
 
 
     // Inicializar el nivel de emision
     let mut emission_intensity = 1.0;
 
+    // Modo de interpolación de atributos (P alterna para comparar con el modo afín)
+    let mut interpolation_mode = InterpolationMode::PerspectiveCorrect;
+
     while window.is_open() {
-        if window.is_key_down(Key::Escape) {
+        input.update(&window);
+        if input.pressed(Action::Quit) {
             break;
         }
     
-        let (rest_models, spaceship_model) = models.split_last_mut().unwrap();
-        handle_input(&window, &mut camera, rest_models, spaceship_model);
+        handle_spaceship_input(&input, &mut spaceship, &mut camera);
+
+        // Recorridos de cámara: Insert graba la pose actual, Home reproduce o detiene, End guarda, Delete borra
+        if input.pressed(Action::RecordKeyframe) && !camera_path.is_playing() {
+            camera_path.record(&camera);
+        }
+        // Al empezar la reproducción se deja la cámara de persecución; detenerla no la cambia
+        if input.pressed(Action::PlayCameraPath) && warp.is_none() && camera_path.toggle_playback(&mut camera) {
+            spaceship.chase_camera = false;
+        }
+        if input.pressed(Action::SaveCameraPath) {
+            if let Err(error) = camera_path.save(DEFAULT_CAMERA_PATH) {
+                eprintln!("{}", error);
+            }
+        }
+        if input.pressed(Action::ClearCameraPath) {
+            camera_path.clear(&mut camera);
+        }
+        let camera_busy = warp.is_some() || camera_path.is_playing();
+
+        // Tab alterna entre la cámara orbital y el vuelo libre (no durante un salto ni un recorrido)
+        if input.pressed(Action::ToggleFreeFly) && !camera_busy {
+            match camera.mode {
+                CameraMode::Orbit => {
+                    // Se deja la cámara de persecución sin cambiar el "arriba" para no saltar
+                    spaceship.chase_camera = false;
+                    camera.enter_free_fly();
+                }
+                CameraMode::FreeFly => camera.leave_free_fly(),
+            }
+        }
+
+        let mouse_position = window.get_mouse_pos(MouseMode::Pass);
+        let mouse_delta = match (mouse_position, last_mouse_position) {
+            (Some((x, y)), Some((last_x, last_y))) => (x - last_x, y - last_y),
+            _ => (0.0, 0.0),
+        };
+        last_mouse_position = mouse_position;
+
+        if !camera_busy && (camera.mode == CameraMode::FreeFly || !spaceship.chase_camera) {
+            match camera.mode {
+                CameraMode::FreeFly => handle_free_fly_input(&input, &mut camera, clock.frame_dt),
+                CameraMode::Orbit => handle_input(&input, &mut camera, &spaceship),
+            }
+            handle_mouse_input(&window, &mut camera, mouse_delta, &input.mouse);
+        }
     
-        handle_key_input(&window, &mut camera, &mut models);
+        if !camera_path.is_playing() {
+            handle_key_input(&input, &mut camera, &models, &mut warp, &scene.description.warp);
+        }
+
+        if input.pressed(Action::ToggleInterpolation) {
+            interpolation_mode = interpolation_mode.toggled();
+        }
+
+        handle_time_input(&input, &mut clock);
+
+        if input.pressed(Action::ToggleNBody) {
+            gravity = match gravity.take() {
+                Some(system) => {
+                    // Al salir del modo los cuerpos vuelven a su órbita kepleriana
+                    system.release(&mut models);
+                    advance_orbits(&mut models, 0.0);
+                    update_world_transforms(&mut models);
+                    None
+                }
+                None => Some(NBodySystem::from_models(&models, GRAVITATIONAL_CONSTANT)),
+            };
+        }
     
         framebuffer.clear();
     
-        // Actualizar la posición y rotación de los modelos
-        for model in &mut models {
-            if model.shader == PlanetShader::Ufo {
-                model.position = camera.eye + camera.get_forward_vector() * 2.5;
-                model.rotation = Vec3::new(camera.pitch, camera.yaw, 0.0);
-            } else if model.shader != PlanetShader::Wormhole {
-                // Incrementar el ángulo de órbita
-                model.orbit_angle += model.orbit_speed;
-        
-                // Asegurarnos de que el ángulo esté en el rango [0, 2*PI]
-                if model.orbit_angle > 2.0 * PI {
-                    model.orbit_angle -= 2.0 * PI;
+        // Simulación a paso fijo: órbitas y giro de los cuerpos
+        let steps = clock.tick();
+        let frame_start: Vec<Vec3> = models.iter().map(|model| model.world_position).collect();
+        for _ in 0..steps.simulation {
+            match &mut gravity {
+                Some(system) => {
+                    system.advance(clock.step_dt());
+                    system.apply(&mut models);
+                    // Lo que cuelga de la nave no está en el sistema y sigue su órbita alrededor de ella
+                    for model in models.iter_mut().filter(|model| model.on_spaceship) {
+                        advance_orbit(model, clock.step_dt());
+                    }
+                }
+                None => advance_orbits(&mut models, clock.step_dt()),
+            }
+            spin_models(&mut models, clock.step_dt());
+            update_world_transforms(&mut models);
+        }
+
+        // La nave vuela en pasos de tiempo real: no se acelera con la escala de tiempo, no corre en
+        // reversa y se sigue pilotando en pausa. Cada paso barre la nave contra su parte del
+        // movimiento de los cuerpos en este frame. Durante un salto o el vuelo libre la nave no
+        // vuela por su cuenta; se coloca después.
+        if warp.is_none() && camera.mode != CameraMode::FreeFly {
+            let frame_end: Vec<Vec3> = models.iter().map(|model| model.world_position).collect();
+            let positions_at = |fraction: f32| -> Vec<Vec3> {
+                frame_start.iter().zip(&frame_end).map(|(start, end)| start + (end - start) * fraction).collect()
+            };
+            let (spaceship_model, rest_models) = models.split_last_mut().unwrap();
+
+            for step in 0..steps.real {
+                let step_start = positions_at(step as f32 / steps.real as f32);
+                let step_end = positions_at((step + 1) as f32 / steps.real as f32);
+
+                // Con la cámara bloqueada en un cuerpo, la nave viaja con él (vuela en su marco de referencia)
+                if let Some(body) = camera.follow_target {
+                    spaceship.position += step_end[body] - step_start[body];
                 }
-        
-                // Actualizar la posición del planeta usando el radio fijo de la órbita
-                model.position.x = model.orbit_radius * model.orbit_angle.cos();
-                model.position.z = model.orbit_radius * model.orbit_angle.sin();
+
+                spaceship.accelerate(FIXED_TIMESTEP, rest_models);
+                let sweeps = body_sweeps(rest_models, &step_start, &step_end);
+                for event in spaceship.fly(FIXED_TIMESTEP, spaceship_model.collision_radius, &sweeps) {
+                    // La cámara de persecución se sacude en la dirección del golpe y luego vuelve sola
+                    if spaceship.chase_camera {
+                        camera.eye += event.normal * (event.impact_speed * COLLISION_JOLT);
+                    }
+                    last_collision = Some((event, COLLISION_MESSAGE_TIME));
+                }
+            }
+            spaceship.sync_model(spaceship_model);
+            update_world_transforms(&mut models);
+        }
+
+        // El salto lleva la cámara hacia el cuerpo y la nave va frente a ella, detenida
+        // En vuelo libre la nave también va frente a la cámara
+        if let Some(flight) = &mut warp {
+            flight.update(&mut camera, &models, clock.frame_dt);
+            spaceship.place_in_front_of(&camera);
+            spaceship.sync_model(models.last_mut().unwrap());
+            update_world_transforms(&mut models);
+        } else if camera_path.is_playing() {
+            // El recorrido mueve solo la cámara; la nave sigue volando por su cuenta
+            camera_path.advance(&mut camera, clock.frame_dt);
+        } else if camera.mode == CameraMode::FreeFly {
+            spaceship.place_in_front_of(&camera);
+            spaceship.sync_model(models.last_mut().unwrap());
+            update_world_transforms(&mut models);
+        } else if spaceship.chase_camera {
+            spaceship.chase(&mut camera, clock.frame_dt);
+        }
+
+        // La cámara bloqueada acompaña al cuerpo en su órbita
+        if let Some(body) = camera.follow_target {
+            if !camera.is_bird_eye_view {
+                camera.track(models[body].world_position);
+            }
+        }
+
+        // El último choque se muestra en el título durante unos segundos
+        last_collision = last_collision
+            .map(|(event, remaining)| (event, remaining - clock.frame_dt))
+            .filter(|(_, remaining)| *remaining > 0.0);
+
+        // Los demás cuerpos que siguen a la cámara se colocan cada frame, no en pasos de simulación
+        let (_, rest_models) = models.split_last_mut().unwrap();
+        for model in rest_models.iter_mut().filter(|model| model.material.follows_camera()) {
+            model.position = camera.eye + camera.get_forward_vector() * 2.5;
+            model.rotation = quat_angle_axis(camera.yaw, &Vec3::new(0.0, 1.0, 0.0));
+        }
+        update_world_transforms(&mut models);
+
+        // Cuerpo que la nave tiene enfrente (rayo desde la nave hacia su punta)
+        let (_, rest_models) = models.split_last().unwrap();
+        let positions: Vec<Vec3> = rest_models.iter().map(|model| model.world_position).collect();
+        let target = ray_cast(&body_sweeps(rest_models, &positions, &positions), &spaceship.position, &spaceship.forward(), TARGET_RANGE);
+
+        // 0 bloquea la cámara en el cuerpo que la nave tiene enfrente, o la libera si ya estaba bloqueada
+        if input.pressed(Action::LockOn) {
+            match (camera.follow_target, target) {
+                (Some(_), _) => camera.release(),
+                (None, Some((body, _))) => {
+                    if spaceship.chase_camera {
+                        spaceship.toggle_chase_camera(&mut camera);
+                    }
+                    camera.lock_on(body, models[body].world_position);
+                }
+                (None, None) => {}
             }
         }
-        
     
         // Crear uniforms antes de renderizar
         let view_matrix = camera.get_view_matrix();
-        let projection_matrix = create_projection_matrix(window_width as f32, window_height as f32);
+        let projection_matrix = create_projection_matrix(window_width as f32, window_height as f32, camera.fov);
         let viewport_matrix = create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-    
-        time_counter += 1;
+
+        // Luces de la escena (las que siguen a un cuerpo toman su posición actual)
+        let lights: Vec<Light> = scene_lights.iter().map(|light| light.resolve(&models)).collect();
     
         let uniforms = Uniforms {
             view_matrix,
@@ -545,9 +604,11 @@ fn main() {
             model_matrix: Mat4::identity(),
             transformation_matrix: Mat4::identity(),
             normal_matrix: Mat3::identity(),
-            time: time_counter,
+            time: clock.time,
             noise: &noise,
             emission_intensity,
+            interpolation_mode,
+            lights: &lights,
         };
     
         // Renderizar el skybox primero
@@ -556,12 +617,25 @@ fn main() {
         // Renderizar las órbitas (éstas son estáticas y no cambian)
         render_orbits(&mut framebuffer, &models, &view_matrix, &projection_matrix);
     
+        // Descarte por frustum: cada modelo se aproxima con su esfera envolvente escalada
+        let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+        let mut cull_stats = CullStats::default();
+        let mut draws = Vec::with_capacity(models.len());
+        let mut screen_triangles = Vec::new();
+
         // Iterar sobre la lista de modelos y renderizar cada uno
         for model in &models {
-            // Crear la matriz de modelo para este modelo
-            let model_matrix = create_model_matrix_with_rotation(model.position, model.scale, model.rotation);
+            cull_stats.models_total += 1;
+            if !frustum.intersects_sphere(&model.world_position, model.mesh_radius * model.scale) {
+                cull_stats.models_culled += 1;
+                continue;
+            }
+
+            // Matriz de modelo ya compuesta con la de sus ancestros
+            let model_matrix = model.world_matrix;
             let transformation_matrix = uniforms.projection_matrix * uniforms.view_matrix * model_matrix;
-            let normal_matrix = model_matrix.fixed_resize::<3, 3>(0.0).try_inverse().unwrap().transpose();
+            // Una matriz no invertible (escala cero) deja las normales como están en lugar de fallar
+            let normal_matrix = model_matrix.fixed_resize::<3, 3>(0.0).try_inverse().unwrap_or_else(Mat3::identity).transpose();
     
             let model_uniforms = Uniforms {
                 model_matrix,
@@ -573,10 +647,71 @@ fn main() {
                 time: uniforms.time,
                 noise: uniforms.noise,
                 emission_intensity: uniforms.emission_intensity,
+                interpolation_mode: uniforms.interpolation_mode,
+                lights: uniforms.lights,
             };
     
-            render(&mut framebuffer, &model_uniforms, model.vertex_array, &model.shader);
+            render(&model_uniforms, model.vertex_array, model.cull_mode, draws.len(), &mut screen_triangles, &mut cull_stats);
+            draws.push(DrawCall { uniforms: model_uniforms, material: model.material });
         }
+
+        // Rasterization Stage:
+        // Convierte los triángulos de todos los modelos en fragmentos por tiles, en paralelo, y los sombrea en el acto.
+        rasterize_tiles(&mut framebuffer, &screen_triangles, interpolation_mode, &|draw, fragment| {
+            let draw = &draws[draw];
+            draw.material.shade(fragment, &draw.uniforms)
+        });
+
+        // Segunda pasada: Combinar el buffer de emisión en el buffer principal
+        framebuffer.blend_emission();
+
+        // Estelas de velocidad mientras dura el salto
+        let warp_status = match &warp {
+            Some(flight) => {
+                framebuffer.apply_warp_streaks(flight.streak_strength() * scene.description.warp.streaks);
+                format!(" | salto a {} {:.0}%", scene.description.body_name(flight.target), flight.progress() * 100.0)
+            }
+            None => String::new(),
+        };
+        // Al terminar un salto con la cámara orbital, ésta queda bloqueada en el cuerpo de destino
+        if let Some(flight) = warp.take_if(|flight| flight.is_finished()) {
+            if !spaceship.chase_camera {
+                camera.lock_on(flight.target, models[flight.target].world_position);
+            }
+        }
+        let follow_status = match camera.follow_target {
+            Some(body) => format!(" | siguiendo a {}", scene.description.body_name(body)),
+            None => String::new(),
+        };
+        let path_status = if camera_path.is_playing() {
+            format!(" | recorrido {:.0}%", camera_path.progress() * 100.0)
+        } else if camera_path.keyframe_count() > 0 {
+            format!(" | recorrido: {} cuadros", camera_path.keyframe_count())
+        } else {
+            String::new()
+        };
+
+        let gravity_status = match &gravity {
+            Some(system) => format!(" | N-cuerpos, deriva de energía {:+.2e}", system.energy_drift()),
+            None => String::new(),
+        };
+        let collision_status = match &last_collision {
+            Some((event, _)) => format!(
+                " | {} {} a {:.1} u/s",
+                if event.bounced { "rebote en" } else { "roce con" },
+                scene.description.body_name(event.body),
+                event.impact_speed,
+            ),
+            None => String::new(),
+        };
+        let target_status = match target {
+            Some((body, distance)) => format!(" | enfrente: {} a {:.1}", scene.description.body_name(body), distance),
+            None => String::new(),
+        };
+        window.set_title(&format!(
+            "Irving 22781 | {}{} | {}{}{}{}{}{} | {}",
+            clock, gravity_status, spaceship, warp_status, follow_status, path_status, target_status, collision_status, cull_stats
+        ));
     
         // Actualizar el buffer de la ventana
         window
@@ -592,101 +727,259 @@ fn main() {
 }
 
 // Función para manejar la selección de shaders y modelos de planetas.
-fn handle_key_input(window: &Window, camera: &mut Camera, models: &mut Vec<Model>) {
-    if window.is_key_down(Key::Key1) {
-        warp_to_planet(camera, &models[1]); // Rocky
+fn handle_key_input(input: &Input, camera: &mut Camera, models: &[Model], warp: &mut Option<Warp>, settings: &WarpDescription) {
+    // Cada cuerpo puede tener asignada una acción de salto en el archivo de escena. Solo cuenta al
+    // presionarla; otra tecla durante un salto cambia el destino desde donde va la cámara.
+    for (index, model) in models.iter().enumerate() {
+        if let Some(action) = model.warp_key {
+            if input.pressed(action) {
+                *warp = Some(Warp::new(camera, models, index, settings.duration, settings.distance));
+                camera.release();
+                camera.leave_free_fly();
+            }
+        }
     }
-    if window.is_key_down(Key::Key2) {
-        warp_to_planet(camera, &models[2]); // Gaseous
+}
+
+// Controles del tiempo de simulación
+fn handle_time_input(input: &Input, clock: &mut SimulationClock) {
+    if input.pressed(Action::TogglePause) {
+        clock.toggle_pause();
     }
-    if window.is_key_down(Key::Key3) {
-        warp_to_planet(camera, &models[3]); // Frozen
+    if input.repeated(Action::StepOnce) {
+        clock.step_once();
     }
-    if window.is_key_down(Key::Key4) {
-        warp_to_planet(camera, &models[4]); // Earth
+    if input.pressed(Action::TimeScale1) {
+        clock.set_time_scale(1.0);
     }
-    if window.is_key_down(Key::Key5) {
-        warp_to_planet(camera, &models[5]); // Oceanic
+    if input.pressed(Action::TimeScale2) {
+        clock.set_time_scale(2.0);
     }
-    if window.is_key_down(Key::Key6) {
-        warp_to_planet(camera, &models[6]); // UFO
+    if input.pressed(Action::TimeScale10) {
+        clock.set_time_scale(10.0);
     }
-    if window.is_key_down(Key::Key7) {
-        warp_to_planet(camera, &models[7]); // Gargantua
+    if input.pressed(Action::TimeScale100) {
+        clock.set_time_scale(100.0);
     }
-    if window.is_key_down(Key::Key8) {
-        warp_to_planet(camera, &models[0]); // Wormhole (al principio de la lista)
+    if input.pressed(Action::ToggleReverse) {
+        clock.toggle_reverse();
     }
 }
 
-fn handle_input(window: &Window, camera: &mut Camera, spaceship_model: &mut Model, models: &mut [Model]) {
+// Cámara orbital alrededor de la nave (cuando la cámara de persecución está apagada)
+fn handle_input(input: &Input, camera: &mut Camera, spaceship: &Spaceship) {
     let orbit_speed = PI / 50.0;
     let zoom_speed = 0.5;
-    let mut new_position = spaceship_model.position;
 
-    // Cambio a Bird Eye View al presionar la tecla B
-    // Cambio a Bird Eye View al presionar la tecla B
-    if window.is_key_down(Key::B) {
+    // Cambio a Bird Eye View al presionar la tecla B (una vez por pulsación)
+    if input.pressed(Action::BirdEyeView) {
         camera.toggle_bird_eye_view();
     }
-    
-
 
     // Movimiento con flechas para orbitar la cámara
-    if window.is_key_down(Key::Left) {
+    if input.held(Action::CameraLeft) {
         camera.orbit(orbit_speed, 0.0);
     }
-    if window.is_key_down(Key::Right) {
+    if input.held(Action::CameraRight) {
         camera.orbit(-orbit_speed, 0.0);
     }
-    if window.is_key_down(Key::Up) {
+    if input.held(Action::CameraUp) {
         camera.orbit(0.0, orbit_speed);
     }
-    if window.is_key_down(Key::Down) {
+    if input.held(Action::CameraDown) {
         camera.orbit(0.0, -orbit_speed);
     }
 
     // Zoom con teclas W y S
-    if window.is_key_down(Key::W) {
+    if input.held(Action::ZoomOut) {
         camera.zoom(-zoom_speed);
     }
-    if window.is_key_down(Key::S) {
+    if input.held(Action::ZoomIn) {
         camera.zoom(zoom_speed);
     }
 
-    // Verificar colisiones antes de actualizar la posición
-    let mut collision_detected = false;
-    for model in models {
-        if model.shader != PlanetShader::Ufo && check_collision(&spaceship_model, &model) {
-            collision_detected = true;
-            break;
+    // Actualizar el centro de la cámara para que apunte siempre hacia la nave si no está en Bird Eye View
+    // ni bloqueada en un cuerpo
+    if !camera.is_bird_eye_view && camera.follow_target.is_none() {
+        camera.center = spaceship.position + camera.pan_offset;
+    }
+}
+
+// Vuelo libre de la cámara: W/S adelante y atrás, A/D a los lados, RePág/AvPág arriba y abajo,
+// flechas para cabeceo y guiñada, Z/X para alabear
+fn handle_free_fly_input(input: &Input, camera: &mut Camera, dt: f32) {
+    let translation = Vec3::new(
+        input.axis(Action::FlyRight, Action::FlyLeft),
+        input.axis(Action::FlyUp, Action::FlyDown),
+        input.axis(Action::FlyForward, Action::FlyBackward),
+    );
+    let turn = Vec3::new(
+        input.axis(Action::CameraUp, Action::CameraDown),
+        input.axis(Action::CameraLeft, Action::CameraRight),
+        input.axis(Action::FlyRollRight, Action::FlyRollLeft),
+    );
+    camera.fly(translation, turn, dt);
+}
+
+// Mouse: arrastrar con el botón izquierdo orbita (en vuelo libre gira la vista), con el derecho
+// desplaza la vista y la rueda acerca o aleja. `delta` es el movimiento del mouse desde el frame anterior.
+fn handle_mouse_input(window: &Window, camera: &mut Camera, delta: (f32, f32), settings: &MouseSettings) {
+    let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+    let dx = delta.0 * settings.sensitivity * sign(settings.invert_x);
+    let dy = delta.1 * settings.sensitivity * sign(settings.invert_y);
+
+    if window.get_mouse_down(MouseButton::Left) {
+        match camera.mode {
+            // Arrastrar a la derecha lleva la cámara hacia la derecha, como la flecha →
+            CameraMode::Orbit => camera.orbit(-dx, -dy),
+            CameraMode::FreeFly => camera.rotate(Vec3::new(-dy, -dx, 0.0)),
         }
     }
 
-    // Manejar la posición según colisión
-    if !collision_detected {
-        spaceship_model.position = new_position;
-    } else {
-        let max_retroceso = 5.0;
-        let backward_vector = -camera.get_forward_vector() * 2.5;
-        let nueva_posicion = spaceship_model.position + backward_vector;
+    // La escena sigue al cursor: el desplazamiento se escala con la distancia al centro
+    if window.get_mouse_down(MouseButton::Right) {
+        let distance = (camera.center - camera.eye).magnitude();
+        camera.pan(-dx * distance, dy * distance);
+    }
 
-        if nalgebra_glm::distance(&spaceship_model.position, &nueva_posicion) < max_retroceso {
-            spaceship_model.position = nueva_posicion;
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        let amount = scroll * settings.zoom_speed * sign(settings.invert_scroll);
+        match camera.mode {
+            // Sin pasar del centro, para no dar la vuelta
+            CameraMode::Orbit => {
+                let distance = (camera.center - camera.eye).magnitude();
+                camera.zoom(amount.min(distance - MIN_ZOOM_DISTANCE));
+            }
+            CameraMode::FreeFly => camera.dolly(amount),
         }
     }
+}
 
-    // Actualizar el centro de la cámara para que apunte siempre hacia la nave si no está en Bird Eye View
-    if !camera.is_bird_eye_view {
-        camera.center = spaceship_model.position;
+// Controles de vuelo de la nave: empuje en sus tres ejes y giro en cabeceo, guiñada y alabeo
+fn handle_spaceship_input(input: &Input, spaceship: &mut Spaceship, camera: &mut Camera) {
+    // T/G adelante y atrás, H/F derecha e izquierda, E/Q arriba y abajo
+    spaceship.throttle = Vec3::new(
+        input.axis(Action::ThrustForward, Action::ThrustBackward),
+        input.axis(Action::ThrustUp, Action::ThrustDown),
+        input.axis(Action::ThrustRight, Action::ThrustLeft),
+    );
+
+    // I/K cabeceo, J/L guiñada, U/O alabeo
+    spaceship.turn = Vec3::new(
+        input.axis(Action::PitchUp, Action::PitchDown),
+        input.axis(Action::YawLeft, Action::YawRight),
+        input.axis(Action::RollRight, Action::RollLeft),
+    );
+
+    // R frena la nave con los propulsores
+    spaceship.braking = input.held(Action::Brake);
+
+    if input.pressed(Action::ToggleChaseCamera) {
+        spaceship.toggle_chase_camera(camera);
+    }
+    if input.pressed(Action::ToggleShipGravity) {
+        spaceship.toggle_gravity();
     }
+}
 
-    // Calcular la rotación de la nave basada en la orientación de la cámara
-    let forward_vector = camera.get_forward_vector();
-    let pitch = forward_vector.y.atan2((forward_vector.x.powi(2) + forward_vector.z.powi(2)).sqrt());
-    let yaw = forward_vector.z.atan2(forward_vector.x);
+// Avanza `dt` segundos a lo largo de las órbitas y resuelve la posición con la ecuación de Kepler
+fn advance_orbits(models: &mut [Model], dt: f32) {
+    for model in models {
+        advance_orbit(model, dt);
+    }
+}
 
-    spaceship_model.rotation = Vec3::new(pitch, yaw, 0.0);
+fn advance_orbit(model: &mut Model, dt: f32) {
+    if let Some(orbit) = &mut model.orbit {
+        orbit.advance(dt);
+        model.position = orbit.position();
+    }
 }
 
+// Avanza `dt` segundos el giro de cada cuerpo sobre su propio eje
+fn spin_models(models: &mut [Model], dt: f32) {
+    for model in models {
+        model.spin += model.rotation_speed * dt;
+        // Mantener los tres ángulos acotados para no perder precisión en sesiones largas
+        model.spin = model.spin.map(|angle| angle % (2.0 * PI));
+    }
+}
 
+// Movimiento de los cuerpos durante un paso de la nave, de `step_start` a `step_end` (posiciones de
+// mundo por modelo), para barrer la nave contra ellos. Las mallas se desplazan a `step_end` con la
+// orientación actual. Los que siguen a la cámara, los que cuelgan de la nave y los que no tienen
+// radio ni malla de colisión no chocan.
+fn body_sweeps<'a>(models: &[Model<'a>], step_start: &[Vec3], step_end: &[Vec3]) -> Vec<BodySweep<'a>> {
+    models
+        .iter()
+        .enumerate()
+        .filter(|(_, model)| !model.material.follows_camera() && !model.on_spaceship)
+        .filter_map(|(index, model)| {
+            // Con malla, la esfera que la envuelve sirve de fase amplia
+            let (radius, mesh) = match model.collision_mesh {
+                Some(bvh) => {
+                    let world_matrix = Mat4::new_translation(&(step_end[index] - model.world_position)) * model.world_matrix;
+                    (model.mesh_radius * model.scale, Some(MeshCollider::new(bvh, world_matrix, model.scale)))
+                }
+                None if model.collision_radius > 0.0 => (model.collision_radius, None),
+                None => return None,
+            };
+            Some(BodySweep {
+                body: index,
+                start: step_start[index],
+                end: step_end[index],
+                radius,
+                mesh,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collision::move_and_collide;
+
+    const ATTACHMENT_SCENE: &str = "src/assets/scenes/spaceship_attachment.toml";
+
+    #[test]
+    fn spaceship_attachments_do_not_collide_with_the_ship() {
+        let materials = MaterialRegistry::with_builtin();
+        let scene = Scene::load(ATTACHMENT_SCENE).unwrap();
+        let mut models = scene.build_models(&materials).unwrap();
+        update_world_transforms(&mut models);
+
+        // La baliza cuelga de la nave y el dron de la baliza
+        let flags: Vec<bool> = models.iter().map(|model| model.on_spaceship).collect();
+        assert_eq!(flags, vec![false, true, true, false]);
+
+        let positions: Vec<Vec3> = models.iter().map(|model| model.world_position).collect();
+        let (spaceship_model, rest_models) = models.split_last().unwrap();
+        let sweeps = body_sweeps(rest_models, &positions, &positions);
+        assert_eq!(sweeps.iter().map(|sweep| sweep.body).collect::<Vec<_>>(), vec![0]);
+
+        // La nave empieza encima de la baliza; sin excluirla habría un choque en t = 0
+        let mut velocity = Vec3::new(0.0, 0.0, 1.0);
+        let start = spaceship_model.world_position;
+        let (end, events) = move_and_collide(start, &mut velocity, spaceship_model.collision_radius, 0.1, &sweeps);
+        assert!(events.is_empty());
+        assert!((end - (start + Vec3::new(0.0, 0.0, 0.1))).magnitude() < 1e-6);
+    }
+
+    #[test]
+    fn spaceship_attachments_stay_out_of_n_body() {
+        let materials = MaterialRegistry::with_builtin();
+        let scene = Scene::load(ATTACHMENT_SCENE).unwrap();
+        let mut models = scene.build_models(&materials).unwrap();
+        update_world_transforms(&mut models);
+        let beacon = models[1].position;
+
+        // El planeta atraería a la baliza si fuera un cuerpo libre
+        let mut system = NBodySystem::from_models(&models, GRAVITATIONAL_CONSTANT);
+        for _ in 0..120 {
+            system.advance(FIXED_TIMESTEP);
+            system.apply(&mut models);
+        }
+        assert_eq!(models[1].position, beacon);
+    }
+}
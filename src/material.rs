@@ -0,0 +1,162 @@
+// material.rs
+// Materiales de los cuerpos de la escena. Cada material decide el color (y la emisión opcional)
+// de sus fragmentos y cómo se comporta el cuerpo en la simulación (si tiene órbita o si sigue a
+// la cámara). Para agregar un cuerpo nuevo basta con implementar `Material` y registrarlo en un
+// `MaterialRegistry`, sin tocar el pipeline de main.rs. Cada material guarda su propio estado
+// (por ejemplo sus generadores de ruido), que se crea una vez junto con el material.
+use std::collections::HashMap;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::Uniforms;
+use crate::noise_cache::{EarthNoise, FrozenNoise, GaseousNoise, RockyNoise, UfoNoise};
+use crate::planets_shader::{rocky_planet_shader,
+                            gaseous_planet_shader,
+                            frozen_planet_shader,
+                            earth_planet_shader,
+                            oceanic_planet_shader,
+                            ufo_shader,
+                            gargantua_shader,
+                            wormhole_shader};
+
+// Los fragmentos se sombrean en paralelo, así que los materiales deben poder compartirse entre hilos
+pub trait Material: Send + Sync {
+    // Color final del fragmento y su emisión (None si el material no emite luz)
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>);
+
+    // El cuerpo gira alrededor del centro del sistema y se dibuja su órbita
+    fn has_orbit(&self) -> bool {
+        true
+    }
+
+    // El cuerpo se coloca frente a la cámara en cada frame (la nave) y no participa en colisiones
+    fn follows_camera(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct RockyMaterial {
+    noises: RockyNoise,
+}
+
+impl Material for RockyMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (rocky_planet_shader(fragment, uniforms, &self.noises), None)
+    }
+}
+
+#[derive(Default)]
+pub struct GaseousMaterial {
+    noises: GaseousNoise,
+}
+
+impl Material for GaseousMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (gaseous_planet_shader(fragment, uniforms, &self.noises), None)
+    }
+}
+
+#[derive(Default)]
+pub struct FrozenMaterial {
+    noises: FrozenNoise,
+}
+
+impl Material for FrozenMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (frozen_planet_shader(fragment, uniforms, &self.noises), None)
+    }
+}
+
+#[derive(Default)]
+pub struct EarthMaterial {
+    noises: EarthNoise,
+}
+
+impl Material for EarthMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (earth_planet_shader(fragment, uniforms, &self.noises), None)
+    }
+}
+
+pub struct OceanicMaterial;
+
+impl Material for OceanicMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (oceanic_planet_shader(fragment, uniforms), None)
+    }
+}
+
+// Metal del OVNI y de la nave; ambos se mantienen frente a la cámara
+#[derive(Default)]
+pub struct UfoMaterial {
+    noises: UfoNoise,
+}
+
+impl Material for UfoMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        (ufo_shader(fragment, uniforms, &self.noises), None)
+    }
+
+    fn has_orbit(&self) -> bool {
+        false
+    }
+
+    fn follows_camera(&self) -> bool {
+        true
+    }
+}
+
+pub struct GargantuaMaterial;
+
+impl Material for GargantuaMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        gargantua_shader(fragment, uniforms)
+    }
+}
+
+// El agujero de gusano es el centro del sistema: no orbita
+pub struct WormholeMaterial;
+
+impl Material for WormholeMaterial {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> (Color, Option<Color>) {
+        wormhole_shader(fragment, uniforms)
+    }
+
+    fn has_orbit(&self) -> bool {
+        false
+    }
+}
+
+// Materiales disponibles por nombre
+pub struct MaterialRegistry {
+    materials: HashMap<String, Box<dyn Material>>,
+}
+
+impl MaterialRegistry {
+    pub fn new() -> Self {
+        MaterialRegistry { materials: HashMap::new() }
+    }
+
+    // Registro con todos los materiales incluidos en el proyecto
+    pub fn with_builtin() -> Self {
+        let mut registry = MaterialRegistry::new();
+        registry.register("rocky", RockyMaterial::default());
+        registry.register("gaseous", GaseousMaterial::default());
+        registry.register("frozen", FrozenMaterial::default());
+        registry.register("earth", EarthMaterial::default());
+        registry.register("oceanic", OceanicMaterial);
+        registry.register("ufo", UfoMaterial::default());
+        registry.register("gargantua", GargantuaMaterial);
+        registry.register("wormhole", WormholeMaterial);
+        registry
+    }
+
+    // Agrega un material o reemplaza al que ya tenía ese nombre
+    pub fn register(&mut self, name: &str, material: impl Material + 'static) {
+        self.materials.insert(name.to_string(), Box::new(material));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Material> {
+        self.materials.get(name).map(|material| material.as_ref())
+    }
+}
@@ -0,0 +1,255 @@
+// nbody.rs
+// Modo de simulación gravitacional: en lugar de seguir sus órbitas keplerianas, los cuerpos se
+// mueven bajo la gravedad mutua de todos los que tienen masa. Se integra con velocity Verlet, que es
+// simplético, así que la energía total oscila alrededor de su valor inicial en lugar de derivar.
+// Las posiciones y velocidades se guardan en espacio de mundo y se escriben de vuelta en los
+// mismos `Model` (relativas a su padre) para que el renderer no cambie.
+use nalgebra_glm::{Mat3, Vec3};
+use crate::Model;
+
+// Constante de gravitación en unidades de la escena (las masas del archivo de escena están pensadas para G = 1)
+pub const GRAVITATIONAL_CONSTANT: f32 = 1.0;
+
+// Paso máximo de la integración en segundos simulados; los pasos más largos (cámara rápida) se
+// dividen en varios para que Verlet siga siendo estable
+const MAX_STEP: f32 = 1.0 / 60.0;
+
+// Suavizado de la distancia para que dos cuerpos muy cercanos no produzcan aceleraciones infinitas
+const SOFTENING: f32 = 0.1;
+
+struct Body {
+    model: usize, // Índice del modelo en la lista de la escena
+    mass: f32,    // Los cuerpos sin masa son partículas de prueba: sienten la gravedad pero no atraen
+    initial_position: Vec3, // Posición local al activar el modo, para devolver a los cuerpos sin órbita
+    position: Vec3,
+    velocity: Vec3,
+    acceleration: Vec3,
+}
+
+pub struct NBodySystem {
+    bodies: Vec<Body>,
+    gravitational_constant: f32,
+    initial_energy: f32,
+}
+
+// Rotación (sin traslación) del marco de un modelo
+fn frame_rotation(model: &Model) -> Mat3 {
+    model.frame.fixed_resize::<3, 3>(0.0)
+}
+
+impl NBodySystem {
+    // Toma el estado actual de la escena. Los cuerpos con órbita parten con la velocidad de una
+    // órbita kepleriana alrededor de su cuerpo central (el padre, o los cuerpos fijos de la raíz)
+    // según la masa de éste; si el centro no tiene masa se usa la velocidad de la órbita del guion.
+    // Los cuerpos que siguen a la cámara no participan.
+    pub fn from_models(models: &[Model], gravitational_constant: f32) -> Self {
+        // Masa que mantienen en órbita los cuerpos de la raíz (los cuerpos fijos, sin órbita ni padre)
+        let root_mass: f32 = models
+            .iter()
+            .filter(|model| model.parent.is_none() && model.orbit.is_none() && !model.material.follows_camera())
+            .map(|model| model.mass)
+            .sum();
+
+        let mut bodies: Vec<Body> = models
+            .iter()
+            .enumerate()
+            // Lo que cuelga de la nave se mueve con ella, no por gravedad
+            .filter(|(_, model)| !model.material.follows_camera() && !model.on_spaceship)
+            .map(|(index, model)| Body {
+                model: index,
+                mass: model.mass,
+                initial_position: model.position,
+                position: model.world_position,
+                velocity: initial_velocity(models, index, root_mass, gravitational_constant),
+                acceleration: Vec3::zeros(),
+            })
+            .collect();
+
+        compute_accelerations(&mut bodies, gravitational_constant);
+
+        let mut system = NBodySystem {
+            bodies,
+            gravitational_constant,
+            initial_energy: 0.0,
+        };
+        system.initial_energy = system.total_energy();
+        system
+    }
+
+    // Avanza `dt` segundos (negativo para correr en reversa) en pasos de a lo más `MAX_STEP`
+    pub fn advance(&mut self, dt: f32) {
+        let substeps = (dt.abs() / MAX_STEP).ceil().max(1.0) as u32;
+        for _ in 0..substeps {
+            self.step(dt / substeps as f32);
+        }
+    }
+
+    // Un paso de velocity Verlet de `dt` segundos (negativo para correr en reversa)
+    fn step(&mut self, dt: f32) {
+        for body in &mut self.bodies {
+            body.velocity += body.acceleration * (0.5 * dt);
+            body.position += body.velocity * dt;
+        }
+
+        compute_accelerations(&mut self.bodies, self.gravitational_constant);
+
+        for body in &mut self.bodies {
+            body.velocity += body.acceleration * (0.5 * dt);
+        }
+    }
+
+    // Escribe las posiciones simuladas en los modelos, convertidas al marco de su padre
+    pub fn apply(&self, models: &mut [Model]) {
+        for body in &self.bodies {
+            let model = &models[body.model];
+            let local = match model.parent {
+                Some(parent) => {
+                    let parent_position = self
+                        .bodies
+                        .iter()
+                        .find(|other| other.model == parent)
+                        .map_or(models[parent].world_position, |other| other.position);
+                    frame_rotation(&models[parent]).transpose() * (body.position - parent_position)
+                }
+                None => body.position,
+            };
+            models[body.model].position = local;
+        }
+    }
+
+    // Devuelve a su posición inicial los cuerpos sin órbita al salir del modo; los que tienen órbita
+    // retoman la suya desde main.rs
+    pub fn release(self, models: &mut [Model]) {
+        for body in self.bodies {
+            if models[body.model].orbit.is_none() {
+                models[body.model].position = body.initial_position;
+            }
+        }
+    }
+
+    // Energía cinética más potencial gravitacional de todos los cuerpos
+    pub fn total_energy(&self) -> f32 {
+        let mut energy = 0.0;
+        for (i, body) in self.bodies.iter().enumerate() {
+            energy += 0.5 * body.mass * body.velocity.magnitude_squared();
+            for other in &self.bodies[i + 1..] {
+                let distance = ((other.position - body.position).magnitude_squared() + SOFTENING * SOFTENING).sqrt();
+                energy -= self.gravitational_constant * body.mass * other.mass / distance;
+            }
+        }
+        energy
+    }
+
+    // Cambio relativo de la energía total desde que se activó el modo
+    pub fn energy_drift(&self) -> f32 {
+        if self.initial_energy == 0.0 {
+            return 0.0;
+        }
+        (self.total_energy() - self.initial_energy) / self.initial_energy.abs()
+    }
+}
+
+fn compute_accelerations(bodies: &mut [Body], gravitational_constant: f32) {
+    for body in bodies.iter_mut() {
+        body.acceleration = Vec3::zeros();
+    }
+
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let offset = bodies[j].position - bodies[i].position;
+            let distance_squared = offset.magnitude_squared() + SOFTENING * SOFTENING;
+            let pull = offset * (gravitational_constant / (distance_squared * distance_squared.sqrt()));
+            let mass_j = bodies[j].mass;
+            let mass_i = bodies[i].mass;
+            bodies[i].acceleration += pull * mass_j;
+            bodies[j].acceleration -= pull * mass_i;
+        }
+    }
+}
+
+// Velocidad en espacio de mundo de un modelo al iniciar la simulación
+fn initial_velocity(models: &[Model], index: usize, root_mass: f32, gravitational_constant: f32) -> Vec3 {
+    let model = &models[index];
+    let (parent_velocity, parent_rotation, central_mass) = match model.parent {
+        Some(parent) => (
+            initial_velocity(models, parent, root_mass, gravitational_constant),
+            frame_rotation(&models[parent]),
+            models[parent].mass,
+        ),
+        None => (Vec3::zeros(), Mat3::identity(), root_mass),
+    };
+
+    let orbit = match &model.orbit {
+        Some(orbit) => orbit,
+        None => return parent_velocity,
+    };
+
+    let scripted = orbit.velocity();
+    let relative = if central_mass > 0.0 && scripted.magnitude() > 0.0 {
+        // Rapidez de la ecuación vis-viva en la dirección de la órbita del guion
+        let radius = orbit.position().magnitude();
+        let speed_squared = gravitational_constant * (central_mass + model.mass)
+            * (2.0 / radius - 1.0 / orbit.semi_major_axis);
+        scripted.normalize() * speed_squared.max(0.0).sqrt()
+    } else {
+        scripted
+    };
+
+    parent_velocity + parent_rotation * relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(mass: f32, position: Vec3, velocity: Vec3) -> Body {
+        Body { model: 0, mass, initial_position: position, position, velocity, acceleration: Vec3::zeros() }
+    }
+
+    // Estrella y planeta en órbita circular, con el centro de masa quieto en el origen
+    fn two_body_orbit() -> NBodySystem {
+        let (star_mass, planet_mass, radius) = (1.0, 0.001, 10.0);
+        let speed = (GRAVITATIONAL_CONSTANT * (star_mass + planet_mass) / radius).sqrt();
+        let total = star_mass + planet_mass;
+        let mut bodies = vec![
+            body(star_mass, Vec3::new(-radius * planet_mass / total, 0.0, 0.0), Vec3::new(0.0, 0.0, -speed * planet_mass / total)),
+            body(planet_mass, Vec3::new(radius * star_mass / total, 0.0, 0.0), Vec3::new(0.0, 0.0, speed * star_mass / total)),
+        ];
+        compute_accelerations(&mut bodies, GRAVITATIONAL_CONSTANT);
+
+        let mut system = NBodySystem { bodies, gravitational_constant: GRAVITATIONAL_CONSTANT, initial_energy: 0.0 };
+        system.initial_energy = system.total_energy();
+        system
+    }
+
+    #[test]
+    fn energy_drift_stays_bounded_over_an_orbit() {
+        let mut system = two_body_orbit();
+        // Dos vueltas completas (el periodo es de unos 200 segundos) en frames de 1/30
+        let mut worst: f32 = 0.0;
+        for _ in 0..12_000 {
+            system.advance(1.0 / 30.0);
+            worst = worst.max(system.energy_drift().abs());
+        }
+        assert!(worst < 1e-3, "energy drift {}", worst);
+    }
+
+    #[test]
+    fn reversing_time_returns_to_the_start() {
+        let mut system = two_body_orbit();
+        let start: Vec<Vec3> = system.bodies.iter().map(|body| body.position).collect();
+
+        for _ in 0..600 {
+            system.advance(1.0 / 30.0);
+        }
+        let moved = (system.bodies[1].position - start[1]).magnitude();
+        assert!(moved > 1.0, "the planet should have moved, moved {}", moved);
+
+        for _ in 0..600 {
+            system.advance(-1.0 / 30.0);
+        }
+        for (body, start) in system.bodies.iter().zip(&start) {
+            assert!((body.position - start).magnitude() < 1e-3, "{:?} != {:?}", body.position, start);
+        }
+    }
+}
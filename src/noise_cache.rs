@@ -0,0 +1,101 @@
+// noise_cache.rs
+// Generadores de ruido de los materiales incluidos, configurados una sola vez en lugar de
+// construirse de nuevo en cada fragmento. Cada material es dueño de los suyos: se crean al crear
+// el material (al registrarlo en el `MaterialRegistry`) y el material se los pasa a su shader.
+use fastnoise_lite::{FastNoiseLite, NoiseType};
+
+fn create_noise(noise_type: NoiseType, frequency: f32) -> FastNoiseLite {
+    let mut noise = FastNoiseLite::new();
+    noise.set_noise_type(Some(noise_type));
+    noise.set_frequency(Some(frequency));
+    noise
+}
+
+// Planeta rocoso: dunas de arena y formaciones rocosas
+pub struct RockyNoise {
+    pub dune: FastNoiseLite,
+    pub rock: FastNoiseLite,
+}
+
+impl Default for RockyNoise {
+    fn default() -> Self {
+        RockyNoise {
+            dune: create_noise(NoiseType::OpenSimplex2, 0.02), // Patrón fluido para dunas grandes
+            rock: create_noise(NoiseType::Cellular, 0.1),      // Celular para rocas y grietas
+        }
+    }
+}
+
+// Gigante gaseoso: bandas amplias y turbulencia
+pub struct GaseousNoise {
+    pub band: FastNoiseLite,
+    pub turbulence: FastNoiseLite,
+}
+
+impl Default for GaseousNoise {
+    fn default() -> Self {
+        GaseousNoise {
+            band: create_noise(NoiseType::Perlin, 0.05),       // Frecuencia baja para bandas amplias
+            turbulence: create_noise(NoiseType::Perlin, 0.2),  // Más alta para detalles de turbulencia
+        }
+    }
+}
+
+// Planeta helado: textura del hielo
+pub struct FrozenNoise {
+    pub ice: FastNoiseLite,
+}
+
+impl Default for FrozenNoise {
+    fn default() -> Self {
+        FrozenNoise {
+            ice: create_noise(NoiseType::Perlin, 0.02),
+        }
+    }
+}
+
+// Corrientes oceánicas de `ocean_currents_shader`
+pub struct CurrentsNoise {
+    pub current: FastNoiseLite,
+    pub wave: FastNoiseLite,
+}
+
+impl Default for CurrentsNoise {
+    fn default() -> Self {
+        CurrentsNoise {
+            current: create_noise(NoiseType::Perlin, 0.08),
+            wave: create_noise(NoiseType::OpenSimplex2, 0.1), // Variaciones de flujo
+        }
+    }
+}
+
+// Tierra: continentes, nubes y las corrientes de sus océanos
+pub struct EarthNoise {
+    pub terrain: FastNoiseLite,
+    pub cloud: FastNoiseLite,
+    pub currents: CurrentsNoise,
+}
+
+impl Default for EarthNoise {
+    fn default() -> Self {
+        EarthNoise {
+            terrain: create_noise(NoiseType::OpenSimplex2S, 0.01), // Detalles del terreno
+            cloud: create_noise(NoiseType::Perlin, 0.02),
+            currents: CurrentsNoise::default(),
+        }
+    }
+}
+
+// UFO: variación de la superficie metálica
+pub struct UfoNoise {
+    pub metal: FastNoiseLite,
+}
+
+impl Default for UfoNoise {
+    fn default() -> Self {
+        UfoNoise {
+            metal: create_noise(NoiseType::OpenSimplex2, 0.005),
+        }
+    }
+}
+
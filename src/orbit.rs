@@ -0,0 +1,165 @@
+// orbit.rs
+// Órbitas keplerianas: elipses definidas por sus elementos orbitales, con la posición del cuerpo
+// resuelta a partir de la ecuación de Kepler. El plano de referencia es XZ (Y hacia arriba) y todas
+// las posiciones son relativas al cuerpo central (el padre del modelo, o el origen).
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Iteraciones máximas y tolerancia del método de Newton para la ecuación de Kepler
+const KEPLER_MAX_ITERATIONS: usize = 16;
+const KEPLER_TOLERANCE: f32 = 1e-6;
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeplerOrbit {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,          // 0 es un círculo, debe ser menor que 1
+    pub inclination: f32,           // Inclinación del plano orbital respecto a XZ (radianes)
+    pub ascending_node: f32,        // Longitud del nodo ascendente (radianes)
+    pub argument_of_periapsis: f32, // Ángulo del periapsis medido desde el nodo ascendente (radianes)
+    pub mean_anomaly: f32,          // Anomalía media actual (radianes)
+    pub mean_motion: f32,           // Avance de la anomalía media por segundo (radianes)
+}
+
+// Resuelve la ecuación de Kepler M = E - e sin(E) para la anomalía excéntrica E (Newton-Raphson)
+pub fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mean_anomaly = wrap_angle(mean_anomaly);
+
+    // Para órbitas muy excéntricas empezar en ±PI (del lado de M) converge mejor que empezar en M
+    let mut eccentric_anomaly = if eccentricity > 0.8 { PI.copysign(mean_anomaly) } else { mean_anomaly };
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let f = eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly;
+        let step = f / (1.0 - eccentricity * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < KEPLER_TOLERANCE {
+            break;
+        }
+    }
+    eccentric_anomaly
+}
+
+// Lleva un ángulo al rango [-PI, PI]
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+impl KeplerOrbit {
+    // Avanza el cuerpo a lo largo de la órbita durante `dt` segundos
+    pub fn advance(&mut self, dt: f32) {
+        self.mean_anomaly = wrap_angle(self.mean_anomaly + self.mean_motion * dt);
+    }
+
+    // Posición actual del cuerpo relativa al cuerpo central
+    pub fn position(&self) -> Vec3 {
+        self.position_at(solve_kepler(self.mean_anomaly, self.eccentricity))
+    }
+
+    // Posición en la elipse para una anomalía excéntrica dada
+    pub fn position_at(&self, eccentric_anomaly: f32) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+
+        // Coordenadas en el plano orbital, con el cuerpo central en un foco y el periapsis sobre +x
+        let x = a * (eccentric_anomaly.cos() - e);
+        let y = b * eccentric_anomaly.sin();
+
+        self.rotate_to_reference_plane(x, y)
+    }
+
+    // Velocidad actual del cuerpo relativa al cuerpo central, según `mean_motion`
+    pub fn velocity(&self) -> Vec3 {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let b = a * (1.0 - e * e).sqrt();
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly, e);
+
+        // dE/dt a partir de derivar la ecuación de Kepler
+        let eccentric_rate = self.mean_motion / (1.0 - e * eccentric_anomaly.cos());
+        let vx = -a * eccentric_anomaly.sin() * eccentric_rate;
+        let vy = b * eccentric_anomaly.cos() * eccentric_rate;
+
+        self.rotate_to_reference_plane(vx, vy)
+    }
+
+    // Lleva un vector del plano orbital (x hacia el periapsis) al plano de referencia de la escena
+    fn rotate_to_reference_plane(&self, x: f32, y: f32) -> Vec3 {
+        // Rotar al plano de referencia: periapsis (ω), inclinación (i) y nodo ascendente (Ω)
+        let (sin_w, cos_w) = self.argument_of_periapsis.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (sin_o, cos_o) = self.ascending_node.sin_cos();
+
+        let px = x * cos_w - y * sin_w;
+        let py = x * sin_w + y * cos_w;
+
+        let ref_x = px * cos_o - py * cos_i * sin_o;
+        let ref_y = px * sin_o + py * cos_i * cos_o;
+        let ref_z = py * sin_i;
+
+        // El eje normal al plano de referencia es Y en la escena
+        Vec3::new(ref_x, ref_z, ref_y)
+    }
+
+    // Puntos de la elipse completa para dibujar la órbita
+    pub fn points(&self, count: usize) -> Vec<Vec3> {
+        (0..count)
+            .map(|i| self.position_at(2.0 * PI * i as f32 / count as f32))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Residuo de la ecuación de Kepler para la anomalía media ya llevada a [-PI, PI]
+    fn kepler_residual(eccentric_anomaly: f32, mean_anomaly: f32, eccentricity: f32) -> f32 {
+        (eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - wrap_angle(mean_anomaly)).abs()
+    }
+
+    #[test]
+    fn circular_orbit_eccentric_anomaly_equals_mean_anomaly() {
+        for i in -8..=8 {
+            let mean_anomaly = i as f32 * 0.39;
+            assert!((solve_kepler(mean_anomaly, 0.0) - mean_anomaly).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn mean_anomaly_is_wrapped_before_solving() {
+        let wrapped = solve_kepler(0.5, 0.3);
+        assert!((solve_kepler(0.5 + 2.0 * PI, 0.3) - wrapped).abs() < 1e-5);
+        assert!((solve_kepler(0.5 - 4.0 * PI, 0.3) - wrapped).abs() < 1e-5);
+    }
+
+    #[test]
+    fn high_eccentricity_converges() {
+        for &eccentricity in &[0.7, 0.9, 0.97, 0.99] {
+            for i in -50..=50 {
+                let mean_anomaly = i as f32 * PI / 50.0;
+                let eccentric_anomaly = solve_kepler(mean_anomaly, eccentricity);
+                assert!(
+                    kepler_residual(eccentric_anomaly, mean_anomaly, eccentricity) < 1e-5,
+                    "e = {}, M = {}, E = {}",
+                    eccentricity,
+                    mean_anomaly,
+                    eccentric_anomaly,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn periapsis_and_apoapsis_distances() {
+        let orbit = KeplerOrbit {
+            semi_major_axis: 10.0,
+            eccentricity: 0.5,
+            inclination: 0.3,
+            ascending_node: 1.1,
+            argument_of_periapsis: 0.7,
+            mean_anomaly: 0.0,
+            mean_motion: 1.0,
+        };
+        assert!((orbit.position_at(0.0).magnitude() - 5.0).abs() < 1e-4);
+        assert!((orbit.position_at(PI).magnitude() - 15.0).abs() < 1e-4);
+    }
+}
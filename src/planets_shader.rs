@@ -1,40 +1,46 @@
 use crate::color::Color;
 use crate::fragment::Fragment;
 use crate::Uniforms;
-use crate::shader::{depth_based_fragment_shader, noise_based_fragment_shader, moving_clouds_shader, ocean_currents_shader};
-use nalgebra_glm::{Vec3, Vec2, vec2, dot};
+use crate::shader::{BASELINE_FRAMES_PER_SECOND, depth_based_fragment_shader, noise_based_fragment_shader, moving_clouds_shader, ocean_currents_shader, object_noise_position};
+use nalgebra_glm::{Vec3, Vec2, vec2};
 use fastnoise_lite::{FastNoiseLite, NoiseType};
 use crate::texture::{init_texture, with_texture};
 use crate::normal_map::{init_normal_map, with_normal_map};
+use crate::light::{diffuse, illuminate};
+use crate::noise_cache::{EarthNoise, FrozenNoise, GaseousNoise, RockyNoise, UfoNoise};
 
-
+// Espacio de iluminación: `fragment.normal` llega en espacio de mundo (el vertex shader la
+// transforma con `Uniforms.normal_matrix`), así que todas las direcciones de luz de este archivo
+// también se expresan en espacio de mundo. `fragment.vertex_position` sigue en espacio de objeto.
+// Las luces vienen de la escena (`Uniforms.lights`, con la estrella central como luz puntual) y se
+// evalúan en `fragment.world_position`, de modo que todos los planetas comparten la misma fuente.
+// El ruido de superficie se muestrea en 3D sobre la posición en espacio de objeto
+// (`object_noise_position`), para que continentes, bandas y grietas queden fijos al planeta.
 
 /// Primer shader de planeta: simula un planeta rocoso con textura granular
-pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &RockyNoise) -> Color {
     // Colores base para simular una superficie árida y terrosa
     let sand_color = Color::new(210, 180, 140);    // Color de arena ocre 210, 180, 140
     let rock_color = Color::new(139, 115, 85);     // Color de roca marrón oscuro
     let cracked_sand_color = Color::new(148, 129, 104); // Arena agrietada beige 220, 200, 160 
 
     // Configuración de ruido para simular dunas de arena y formaciones rocosas
-    let mut dune_noise = FastNoiseLite::new();
-    dune_noise.set_noise_type(Some(NoiseType::OpenSimplex2));  // Patrón fluido para dunas
-    dune_noise.set_frequency(Some(0.02));                      // Frecuencia baja para dunas grandes 0.02
+    let dune_noise = &noises.dune;             // Patrón fluido para dunas
 
-    let mut rock_noise = FastNoiseLite::new();
-    rock_noise.set_noise_type(Some(NoiseType::Cellular));      // Celular para rocas y grietas
-    rock_noise.set_frequency(Some(0.1));                       // Frecuencia media para detalles en roca
+    let rock_noise = &noises.rock;             // Celular para rocas y grietas
 
     // Animación del polvo en el viento
-    let time_factor = uniforms.time as f32 * 0.5;            // Control de velocidad de movimiento del polvo
-    let dust_movement = dune_noise.get_noise_2d(
-        fragment.position.x + time_factor,
-        fragment.position.y + time_factor,
+    let p = object_noise_position(fragment);
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.5;            // Control de velocidad de movimiento del polvo
+    let dust_movement = dune_noise.get_noise_3d(
+        p.x + time_factor,
+        p.y + time_factor,
+        p.z,
     );
 
     // Obtener el valor de ruido para las dunas y las formaciones rocosas
-    let dune_value = dune_noise.get_noise_2d(fragment.position.x, fragment.position.y);
-    let rock_value = rock_noise.get_noise_2d(fragment.position.x, fragment.position.y);
+    let dune_value = dune_noise.get_noise_3d(p.x, p.y, p.z);
+    let rock_value = rock_noise.get_noise_3d(p.x, p.y, p.z);
 
     // Asignación de color en función del valor de ruido
     let surface_color = if dune_value > 0.3 {
@@ -47,9 +53,7 @@ pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let dusty_color = surface_color.lerp(&sand_color, dust_movement.abs() * 0.2);
 
     // Iluminación intensa y sombras para resaltar el relieve
-    let light_dir = Vec3::new(1.0, -1.0, 0.5).normalize();
-    let intensity = fragment.normal.dot(&light_dir).max(0.0);
-    let illuminated_color = dusty_color * (0.6 + 0.4 * intensity);
+    let illuminated_color = illuminate(dusty_color, 0.6, 0.4, uniforms.lights, &fragment.normal, &fragment.world_position);
 
     // Efecto de niebla atmosférica en el horizonte
     let distance_from_center = fragment.vertex_position.norm();
@@ -62,7 +66,7 @@ pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 /// Segundo shader de planeta: simula un planeta como Jupiter
-pub fn gaseous_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn gaseous_planet_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &GaseousNoise) -> Color {
     // Colores base para las bandas de Júpiter
     let dark_brown = Color::new(139, 69, 19);      // Marrón oscuro
     let beige = Color::new(222, 184, 135);         // Beige claro
@@ -74,20 +78,17 @@ pub fn gaseous_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
     let spot_highlight = Color::new(255, 160, 122); // Tono rosado para detalles
 
     // Parámetros de ruido para simular las bandas y la turbulencia
-    let mut band_noise = FastNoiseLite::new();
-    band_noise.set_noise_type(Some(NoiseType::Perlin));
-    band_noise.set_frequency(Some(0.05));          // Frecuencia baja para bandas amplias
-
-    let mut turbulence_noise = FastNoiseLite::new();
-    turbulence_noise.set_noise_type(Some(NoiseType::Perlin));
-    turbulence_noise.set_frequency(Some(0.2));     // Frecuencia más alta para detalles de turbulencia
+    let band_noise = &noises.band;             // Frecuencia baja para bandas amplias
+    let turbulence_noise = &noises.turbulence; // Frecuencia más alta para turbulencia
 
     // Animación de bandas horizontales y rotación
-    let time_factor = uniforms.time as f32 * 0.03; // Control de velocidad de rotación
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.03; // Control de velocidad de rotación
 
-    // Generar ruido para las bandas y turbulencia
-    let band_value = band_noise.get_noise_2d(fragment.position.x, fragment.position.y + time_factor);
-    let turbulence_value = turbulence_noise.get_noise_2d(fragment.position.x + time_factor, fragment.position.y);
+    // Generar ruido para las bandas y turbulencia. Las bandas dependen sobre todo de la latitud (y),
+    // por eso x y z se comprimen para estirar el ruido a lo largo de los paralelos.
+    let p = object_noise_position(fragment);
+    let band_value = band_noise.get_noise_3d(p.x * 0.2, p.y + time_factor, p.z * 0.2);
+    let turbulence_value = turbulence_noise.get_noise_3d(p.x + time_factor, p.y, p.z);
 
     // Selección de color basado en el valor de ruido para bandas horizontales
     let band_color = if band_value < -0.2 {
@@ -104,28 +105,26 @@ pub fn gaseous_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
     let turbulent_color = band_color.lerp(&beige, turbulence_value.abs() * 0.3);
 
     // Gran Mancha Roja - Agregada como una textura circular
-    let spot_position = nalgebra_glm::vec2(0.3, -0.5); // Posición fija en coordenadas del planeta
-    let fragment_position = nalgebra_glm::vec2(fragment.position.x, fragment.position.y);
-    let spot_distance = nalgebra_glm::distance(&fragment_position, &spot_position);
+    let spot_direction = Vec3::new(0.3, -0.5, 0.8).normalize(); // Dirección fija en coordenadas del planeta
+    let fragment_direction = fragment.vertex_position.normalize();
+    let spot_distance = nalgebra_glm::distance(&fragment_direction, &spot_direction);
 
-    let red_spot_effect = if spot_distance < 0.1 {
-        let spot_intensity = ((0.1 - spot_distance) / 0.1).clamp(0.0, 1.0); // Intensidad más alta en el centro
+    let red_spot_effect = if spot_distance < 0.25 {
+        let spot_intensity = ((0.25 - spot_distance) / 0.25).clamp(0.0, 1.0); // Intensidad más alta en el centro
         red_spot_color.lerp(&spot_highlight, spot_intensity)
     } else {
         turbulent_color // Sin efecto fuera del área de la mancha
     };
 
     // Efecto de sombreado en los bordes para simular la curvatura
-    let light_dir = Vec3::new(1.0, 1.0, -1.0).normalize();
-    let intensity = fragment.normal.dot(&light_dir).max(0.0);
-    let shaded_color = red_spot_effect * (0.7 + 0.3 * intensity); // Mezcla sombreada
+    let shaded_color = illuminate(red_spot_effect, 0.7, 0.3, uniforms.lights, &fragment.normal, &fragment.world_position); // Mezcla sombreada
 
     // Aplicar un ajuste de profundidad para una apariencia tridimensional
     depth_based_fragment_shader(fragment, shaded_color)
 }
 
 /// Tercer shader de planeta: simula un planeta congelado
-pub fn frozen_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn frozen_planet_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &FrozenNoise) -> Color {
     // Colores base: azul claro para el hielo y blanco para la nieve
     let ice_color = Color::new(19, 62, 135); // Azul hielo (mayoría) rgb() rgb()
     let snow_color = Color::new(255, 255, 255); // Blanco nieve
@@ -134,17 +133,16 @@ pub fn frozen_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
 
     // Configurar el ruido para crear textura de hielo
-    let mut noise = FastNoiseLite::new();
-    noise.set_noise_type(Some(NoiseType::Perlin));
-    noise.set_frequency(Some(0.02));
+    let noise = &noises.ice;
 
     // Obtener valores de ruido para la textura de hielo y nieve
-    let noise_value = noise.get_noise_2d(fragment.position.x, fragment.position.y);
+    let p = object_noise_position(fragment);
+    let noise_value = noise.get_noise_3d(p.x, p.y, p.z);
     let normalized_noise = 0.1 * ((noise_value + 1.0) / 5.0);
 
     //Fog
-    let time_factor = uniforms.time as f32 * 2.1; // Ajusta la velocidad de movimiento de las nubes
-    let noiseFog_value = 0.5 * uniforms.noise.get_noise_2d(fragment.position.x + time_factor, fragment.position.y);
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 2.1; // Ajusta la velocidad de movimiento de las nubes
+    let noiseFog_value = 0.5 * uniforms.noise.get_noise_3d(p.x + time_factor, p.y, p.z);
     let fog_opacity = (noiseFog_value + 1.0) / 1.0;
 
 
@@ -158,9 +156,7 @@ pub fn frozen_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     };
 
     // Efecto de brillo y reflejo
-    let light_dir = Vec3::new(1.0, 1.0, -1.0).normalize();
-    let intensity = fragment.normal.dot(&light_dir).max(0.0);
-    let reflective_color = surface_color * (0.7 + 0.3 * intensity); // Ajuste de brillo reducido
+    let reflective_color = illuminate(surface_color, 0.7, 0.3, uniforms.lights, &fragment.normal, &fragment.world_position); // Ajuste de brillo reducido
 
     let blended_color = reflective_color.lerp(&cloud_color, fog_opacity);
     // Aplicar un ajuste de profundidad para el sombreado
@@ -168,26 +164,26 @@ pub fn frozen_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 }
 
 /// Cuarto shader de planeta: simula el planeta Tierra
-pub fn earth_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn earth_planet_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &EarthNoise) -> Color {
     // Colores base para continentes
     let land_color = Color::new(34, 139, 34);          // Verde para continentes
     let mountain_color = Color::new(139, 69, 19);      // Marrón para montañas y elevaciones
 
     // Configurar ruido para texturas de continentes
-    let mut terrain_noise = FastNoiseLite::new();
-    terrain_noise.set_noise_type(Some(NoiseType::OpenSimplex2S));
-    terrain_noise.set_frequency(Some(0.01));            // Frecuencia para detalles del terreno
+    let terrain_noise = &noises.terrain;   // Frecuencia para detalles del terreno
 
     // Animación del movimiento tectónico en los continentes
-    let tectonic_time_factor = uniforms.time as f32 * 0.09; // Velocidad de desplazamiento de placas
-    let terrain_value = terrain_noise.get_noise_2d(
-        fragment.position.x + tectonic_time_factor, 
-        fragment.position.y + tectonic_time_factor,
+    let p = object_noise_position(fragment);
+    let tectonic_time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.09; // Velocidad de desplazamiento de placas
+    let terrain_value = terrain_noise.get_noise_3d(
+        p.x + tectonic_time_factor,
+        p.y + tectonic_time_factor,
+        p.z,
     );
 
     // Asignación de colores en función del ruido para el océano y los continentes
     let base_terrain_color = if terrain_value < -0.3 {
-        ocean_currents_shader(fragment, uniforms) // Llamada al shader de corrientes oceánicas, sin animación
+        ocean_currents_shader(fragment, uniforms, &noises.currents) // Llamada al shader de corrientes oceánicas, sin animación
     } else if terrain_value < 0.1 {
         land_color.lerp(&mountain_color, terrain_value.abs() * 0.4)    // Textura de relieve en tierra
     } else {
@@ -195,15 +191,14 @@ pub fn earth_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     };
 
     // Nubes en movimiento usando ruido desplazado
-    let mut cloud_noise = FastNoiseLite::new();
-    cloud_noise.set_noise_type(Some(NoiseType::Perlin));
-    cloud_noise.set_frequency(Some(0.02));
+    let cloud_noise = &noises.cloud;
 
     // Animación de las nubes con desplazamiento por tiempo
-    let cloud_time_factor = uniforms.time as f32 * 0.8;
-    let cloud_value = cloud_noise.get_noise_2d(
-        fragment.position.x + cloud_time_factor,
-        fragment.position.y,
+    let cloud_time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.8;
+    let cloud_value = cloud_noise.get_noise_3d(
+        p.x + cloud_time_factor,
+        p.y,
+        p.z,
     );
     let cloud_opacity = ((cloud_value + 1.0) / 2.0).clamp(0.0, 1.0);
 
@@ -216,7 +211,10 @@ pub fn earth_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let atmosphere_color = Color::new(135, 206, 250);
 
     // Interpolación final con la atmósfera
-    let final_color = cloud_layer_color.lerp(&atmosphere_color, atmosphere_intensity);
+    let atmosphere_color = cloud_layer_color.lerp(&atmosphere_color, atmosphere_intensity);
+
+    // Iluminación de la estrella: lado diurno y nocturno
+    let final_color = illuminate(atmosphere_color, 0.25, 0.9, uniforms.lights, &fragment.normal, &fragment.world_position);
 
     // Sombreado final
     depth_based_fragment_shader(fragment, final_color)
@@ -240,15 +238,17 @@ pub fn oceanic_planet_shader1(fragment: &Fragment, uniforms: &Uniforms) -> Color
     large_wave_noise.set_frequency(Some(0.02));          // Ondas amplias para simular el flujo del agua
 
     // Animación para dar movimiento a las ondas
-    let time_factor = uniforms.time as f32 * 0.2;
-    let wave_value = wave_noise.get_noise_2d(fragment.position.x + time_factor, fragment.position.y);
-    let large_wave_value = large_wave_noise.get_noise_2d(fragment.position.x, fragment.position.y + time_factor);
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.2;
+    let p = object_noise_position(fragment);
+    let wave_value = wave_noise.get_noise_3d(p.x + time_factor, p.y, p.z);
+    let large_wave_value = large_wave_noise.get_noise_3d(p.x, p.y + time_factor, p.z);
 
     // Colores de ondas, combinando profundidad y movimiento de agua
     let ocean_wave_color = deep_ocean_color.lerp(&shallow_ocean_color, (wave_value * 0.4 + large_wave_value * 0.6).abs());
 
     // Especularidad para reflejos de luz
-    let light_dir = Vec3::new(1.0, 1.0, -0.5).normalize();
+    let light_dir = uniforms.lights.first()
+        .map_or(Vec3::new(1.0, 1.0, -0.5).normalize(), |light| light.direction_to(&fragment.world_position));
     let reflect_dir = 2.0 * fragment.normal.dot(&light_dir) * fragment.normal - light_dir;
     let specular_intensity = reflect_dir.dot(&fragment.normal).max(0.0).powf(25.0);
 
@@ -265,7 +265,7 @@ pub fn oceanic_planet_shader1(fragment: &Fragment, uniforms: &Uniforms) -> Color
     depth_based_fragment_shader(fragment, final_color)
 }
 
-pub fn oceanic_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+pub fn oceanic_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Obtener el color base de la textura
     let base_color = with_texture(|texture| {
         texture.sample(fragment.tex_coords.x, fragment.tex_coords.y)
@@ -279,37 +279,34 @@ pub fn oceanic_planet_shader(fragment: &Fragment, _uniforms: &Uniforms) -> Color
     // Modificar la normal del fragmento usando el mapa normal
     let modified_normal = (fragment.normal + normal_from_map).normalize();
 
-    // Calcular la iluminación con la nueva normal
-    let light_dir = Vec3::new(0.0, 0.0, 1.0);
-    let intensity = dot(&modified_normal, &light_dir).max(0.0);
-
-    // Aplicar la intensidad al color base
-    base_color * intensity
+    // Calcular la iluminación con la nueva normal y aplicarla al color base
+    illuminate(base_color, 0.0, 1.0, uniforms.lights, &modified_normal, &fragment.world_position)
 }
 
 
 /// Sexto shader: UFO
-pub fn ufo_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn ufo_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &UfoNoise) -> Color {
     // Colores base para el metal y el aura
     let metallic_color = Color::new(192, 192, 192);      // Plateado metálico
     let highlight_color = Color::new(255, 255, 255);     // Blanco para reflejos en el metal
     let aura_color = Color::new(200, 200, 255);          // Azul tenue para el aura
 
     // Textura metálica usando ruido para simular variación de superficie
-    let mut metal_noise = FastNoiseLite::new();
-    metal_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    metal_noise.set_frequency(Some(0.005));               // Frecuencia para textura del metal
+    let metal_noise = &noises.metal;         // Frecuencia para textura del metal
 
-    let reflection_value = (metal_noise.get_noise_2d(fragment.position.x, fragment.position.y) * 1.5).clamp(-1.0, 0.0); 
-    let metallic_surface = metallic_color.lerp(&highlight_color, reflection_value.abs() * 0.5 * fragment.intensity); // Ajuste de intensidad del reflejo
+    let p = object_noise_position(fragment);
+    let reflection_value = (metal_noise.get_noise_3d(p.x, p.y, p.z) * 1.5).clamp(-1.0, 0.0);
+    let light = diffuse(uniforms.lights, &fragment.normal, &fragment.world_position).min(1.0); // El reflejo solo aparece del lado iluminado
+    let metallic_surface = metallic_color.lerp(&highlight_color, reflection_value.abs() * 0.5 * light); // Ajuste de intensidad del reflejo
 
     // Efecto de aura alrededor del objeto
     let distance_from_center = fragment.vertex_position.norm();
     let aura_intensity = (0.6 - distance_from_center).clamp(0.0, 1.0) * 0.5; // Intensidad del aura suave
     let aura_effect = metallic_surface.lerp(&aura_color, aura_intensity);
+    let lit_color = illuminate(aura_effect, 0.4, 0.8, uniforms.lights, &fragment.normal, &fragment.world_position);
 
     // Color final con textura metálica y aura
-    depth_based_fragment_shader(fragment, aura_effect)
+    depth_based_fragment_shader(fragment, lit_color)
 }
 
 /// Séptimmo shader: black hole
@@ -344,7 +341,7 @@ pub fn wormhole_shader(fragment: &Fragment, uniforms: &Uniforms) -> (Color, Opti
     let position = fragment.vertex_position;
     let distance_from_center = position.norm();
 
-    let time = uniforms.time as f32 * 0.5;
+    let time = uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.5;
     let cos_angle = time.cos();
     let sin_angle = time.sin();
 
@@ -450,7 +447,7 @@ pub fn gargantua_shader1(fragment: &Fragment, uniforms: &Uniforms) -> (Color, Op
     let in_accretion_disk = distance_from_center > disk_inner_radius && distance_from_center < disk_outer_radius;
 
     // Intensidad de pulso basada en `vertex_position` y `time`
-    let pulse_intensity = ((uniforms.time as f32 * 0.3).sin() * 0.5 + 0.5) * 1.5;
+    let pulse_intensity = ((uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.3).sin() * 0.5 + 0.5) * 1.5;
     let mut final_disk_color = event_horizon_color;
 
     if in_accretion_disk {
@@ -0,0 +1,465 @@
+// scene.rs
+// Descripción del sistema en un archivo TOML: cuerpos, mallas, materiales, órbitas, luces, cámara
+// inicial y skybox. Se carga al iniciar, así que se pueden armar sistemas nuevos sin recompilar.
+// Los errores de validación indican la entrada del archivo que los provocó.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use nalgebra_glm::{Mat4, Quat, Vec3};
+use serde::Deserialize;
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::collision::CollisionShape;
+use crate::color::Color;
+use crate::culling::{mesh_bounding_radius, CullMode};
+use crate::input::WARP_ACTIONS;
+use crate::light::Light;
+use crate::material::MaterialRegistry;
+use crate::obj::Obj;
+use crate::orbit::KeplerOrbit;
+use crate::vertex::Vertex;
+use crate::Model;
+
+// Escena que se carga si no se pasa otra como argumento
+pub const DEFAULT_SCENE_PATH: &str = "src/assets/scenes/solar_system.toml";
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, message: String },
+    // `entry` identifica la entrada del archivo, por ejemplo `bodies[3] ("earth")`
+    Invalid { entry: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "Failed to read scene {}: {}", path, source),
+            SceneError::Parse { path, message } => write!(f, "Failed to parse scene {}: {}", path, message),
+            SceneError::Invalid { entry, message } => write!(f, "Invalid scene entry {}: {}", entry, message),
+        }
+    }
+}
+
+fn invalid(entry: &str, message: String) -> SceneError {
+    SceneError::Invalid { entry: entry.to_string(), message }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub skybox: SkyboxDescription,
+    #[serde(default)]
+    pub warp: WarpDescription,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    #[serde(default)]
+    pub bodies: Vec<BodyDescription>,
+    // La nave del jugador; siempre queda al final de la lista de modelos
+    pub spaceship: BodyDescription,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub eye: [f32; 3],
+    #[serde(default)]
+    pub center: [f32; 3],
+    #[serde(default = "default_up")]
+    pub up: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyboxDescription {
+    pub star_count: usize,
+}
+
+impl Default for SkyboxDescription {
+    fn default() -> Self {
+        SkyboxDescription { star_count: 900 }
+    }
+}
+
+// Saltos animados a los cuerpos con `warp_key`
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WarpDescription {
+    pub duration: f32, // Segundos reales que dura el vuelo
+    pub distance: f32, // Distancia al cuerpo a la que termina el salto
+    pub streaks: f32,  // Largo máximo de las estelas de velocidad (fracción de la pantalla, 0 las apaga)
+}
+
+impl Default for WarpDescription {
+    fn default() -> Self {
+        WarpDescription { duration: 2.0, distance: 15.0, streaks: 0.15 }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum LightDescription {
+    // Luz puntual fija en `position`, o que sigue al cuerpo llamado `body`
+    Point {
+        position: Option<[f32; 3]>,
+        body: Option<String>,
+        color: [u8; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Directional {
+        direction: [f32; 3],
+        color: [u8; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyDescription {
+    pub name: String,
+    pub mesh: String,     // Ruta del archivo OBJ
+    pub material: String, // Nombre en el `MaterialRegistry`
+    pub parent: Option<String>, // Cuerpo al que está unido; su órbita y posición son relativas a él
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub position: [f32; 3], // Para cuerpos sin órbita, relativa al padre
+    #[serde(default)]
+    pub rotation_speed: [f32; 3], // Radianes por segundo
+    #[serde(default = "default_collision")]
+    pub collision: CollisionShape,
+    pub collision_radius: Option<f32>, // Solo para `collision = "sphere"`; sin radio el cuerpo no choca
+    #[serde(default)]
+    pub mass: f32, // Solo la usa el modo de gravedad N-cuerpos; 0 es una partícula de prueba
+    #[serde(default = "default_cull")]
+    pub cull: CullMode,
+    pub orbit: Option<OrbitDescription>,
+    pub warp_key: Option<usize>, // Acción de salto (1-9, `warp1` a `warp9` en las teclas) que lleva al cuerpo
+}
+
+// Elementos orbitales keplerianos alrededor del padre (o del origen). Los ángulos van en grados;
+// el plano de referencia es XZ
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrbitDescription {
+    pub semi_major_axis: f32,
+    #[serde(default)]
+    pub eccentricity: f32,
+    #[serde(default)]
+    pub inclination: f32,
+    #[serde(default)]
+    pub ascending_node: f32,
+    #[serde(default)]
+    pub argument_of_periapsis: f32,
+    #[serde(default)]
+    pub mean_anomaly: f32, // Posición inicial en la órbita
+    #[serde(default)]
+    pub speed: f32, // Avance de la anomalía media en radianes por segundo
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_collision() -> CollisionShape {
+    CollisionShape::Sphere
+}
+
+fn default_cull() -> CullMode {
+    CullMode::Back
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+// Malla cargada una sola vez y compartida por todos los cuerpos que la usan
+pub struct LoadedMesh {
+    pub vertices: Vec<Vertex>,
+    pub radius: f32,
+    pub bvh: Option<Bvh>, // Solo si algún cuerpo la usa con `collision = "mesh"`
+}
+
+// Luz de la escena; si está unida a un cuerpo, su posición se actualiza con la de ese modelo
+pub struct SceneLight {
+    light: Light,
+    body: Option<usize>,
+}
+
+impl SceneLight {
+    pub fn resolve(&self, models: &[Model]) -> Light {
+        match self.body {
+            Some(index) => Light::point(models[index].world_position, self.light.color, self.light.intensity),
+            None => self.light,
+        }
+    }
+}
+
+pub struct Scene {
+    pub description: SceneDescription,
+    pub meshes: HashMap<String, LoadedMesh>,
+}
+
+impl Scene {
+    pub fn load(path: &str) -> Result<Self, SceneError> {
+        let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        let description: SceneDescription = toml::from_str(&source).map_err(|error| SceneError::Parse {
+            path: path.to_string(),
+            message: error.to_string(),
+        })?;
+
+        let warp = &description.warp;
+        if warp.duration < 0.0 {
+            return Err(invalid("warp", format!("duration must not be negative, got {}", warp.duration)));
+        }
+        if warp.distance <= 0.0 {
+            return Err(invalid("warp", format!("distance must be positive, got {}", warp.distance)));
+        }
+        if !(0.0..=1.0).contains(&warp.streaks) {
+            return Err(invalid("warp", format!("streaks must be between 0 and 1, got {}", warp.streaks)));
+        }
+
+        // Cargar cada malla distinta una sola vez
+        let mut meshes: HashMap<String, LoadedMesh> = HashMap::new();
+        for (entry, body) in description.entries() {
+            if !meshes.contains_key(&body.mesh) {
+                let obj = Obj::load(&body.mesh)
+                    .map_err(|error| invalid(&entry, format!("failed to load mesh {}: {}", body.mesh, error)))?;
+                let vertices = obj.get_vertex_array();
+                let radius = mesh_bounding_radius(&vertices);
+                meshes.insert(body.mesh.clone(), LoadedMesh { vertices, radius, bvh: None });
+            }
+
+            // La BVH se construye solo para las mallas que algún cuerpo usa para chocar
+            let mesh = meshes.get_mut(&body.mesh).unwrap();
+            if body.collision == CollisionShape::Mesh && mesh.bvh.is_none() {
+                mesh.bvh = Some(Bvh::from_vertices(&mesh.vertices));
+            }
+        }
+
+        Ok(Scene { description, meshes })
+    }
+
+    pub fn camera(&self) -> Camera {
+        let camera = &self.description.camera;
+        Camera::new(vec3(camera.eye), vec3(camera.center), vec3(camera.up))
+    }
+
+    // Construye los modelos de la escena (la nave al final), validando cada cuerpo
+    pub fn build_models<'a>(&'a self, materials: &'a MaterialRegistry) -> Result<Vec<Model<'a>>, SceneError> {
+        let mut names: HashMap<&str, String> = HashMap::new();
+        let mut warp_keys: HashMap<usize, String> = HashMap::new();
+        let mut models = Vec::with_capacity(self.description.bodies.len() + 1);
+
+        for (index, (entry, body)) in self.description.entries().enumerate() {
+            let is_spaceship = index == self.description.bodies.len();
+            if body.name.is_empty() {
+                return Err(invalid(&entry, "name must not be empty".to_string()));
+            }
+            if let Some(previous) = names.insert(&body.name, entry.clone()) {
+                return Err(invalid(&entry, format!("name \"{}\" is already used by {}", body.name, previous)));
+            }
+
+            let material = materials.get(&body.material)
+                .ok_or_else(|| invalid(&entry, format!("unknown material \"{}\"", body.material)))?;
+            if !(body.scale.is_finite() && body.scale > 0.0) {
+                return Err(invalid(&entry, format!("scale must be a positive finite number, got {}", body.scale)));
+            }
+            match (body.collision, body.collision_radius) {
+                (CollisionShape::Mesh, Some(_)) => {
+                    return Err(invalid(&entry, "collision_radius is only used with collision = \"sphere\"".to_string()));
+                }
+                (_, Some(radius)) if radius < 0.0 => {
+                    return Err(invalid(&entry, format!("collision_radius must not be negative, got {}", radius)));
+                }
+                _ => {}
+            }
+            if body.mass < 0.0 {
+                return Err(invalid(&entry, format!("mass must not be negative, got {}", body.mass)));
+            }
+
+            let warp_key = match body.warp_key {
+                // Saltar a la nave no tiene sentido: la cámara ya la sigue
+                Some(_) if is_spaceship => {
+                    return Err(invalid(&entry, "the spaceship cannot have a warp_key".to_string()));
+                }
+                Some(number) => {
+                    if !(1..=WARP_ACTIONS.len()).contains(&number) {
+                        return Err(invalid(&entry, format!("warp_key must be between 1 and 9, got {}", number)));
+                    }
+                    if let Some(previous) = warp_keys.insert(number, entry.clone()) {
+                        return Err(invalid(&entry, format!("warp_key {} is already used by {}", number, previous)));
+                    }
+                    Some(WARP_ACTIONS[number - 1])
+                }
+                None => None,
+            };
+
+            let (position, orbit) = match (&body.orbit, material.has_orbit()) {
+                (Some(orbit), true) => {
+                    if orbit.semi_major_axis <= 0.0 {
+                        return Err(invalid(&entry, format!("orbit semi_major_axis must be positive, got {}", orbit.semi_major_axis)));
+                    }
+                    if !(0.0..1.0).contains(&orbit.eccentricity) {
+                        return Err(invalid(&entry, format!("orbit eccentricity must be in [0, 1), got {}", orbit.eccentricity)));
+                    }
+                    let orbit = KeplerOrbit {
+                        semi_major_axis: orbit.semi_major_axis,
+                        eccentricity: orbit.eccentricity,
+                        inclination: orbit.inclination.to_radians(),
+                        ascending_node: orbit.ascending_node.to_radians(),
+                        argument_of_periapsis: orbit.argument_of_periapsis.to_radians(),
+                        mean_anomaly: orbit.mean_anomaly.to_radians(),
+                        mean_motion: orbit.speed,
+                    };
+                    (orbit.position(), Some(orbit))
+                }
+                (None, _) => (vec3(body.position), None),
+                (Some(_), false) => {
+                    return Err(invalid(&entry, format!("material \"{}\" does not orbit, remove [orbit]", body.material)));
+                }
+            };
+
+            let parent = match &body.parent {
+                Some(name) => {
+                    if material.follows_camera() {
+                        return Err(invalid(&entry, format!("material \"{}\" follows the camera and cannot have a parent", body.material)));
+                    }
+                    Some(self.description.body_index(name)
+                        .ok_or_else(|| invalid(&entry, format!("unknown parent \"{}\"", name)))?)
+                }
+                None => None,
+            };
+
+            let mesh = &self.meshes[&body.mesh];
+            models.push(Model {
+                vertex_array: &mesh.vertices,
+                material,
+                parent,
+                position,
+                scale: body.scale,
+                rotation: Quat::identity(),
+                rotation_speed: vec3(body.rotation_speed),
+                spin: Vec3::zeros(),
+                collision_radius: body.collision_radius.unwrap_or(0.0),
+                collision_mesh: mesh.bvh.as_ref().filter(|_| body.collision == CollisionShape::Mesh),
+                mass: body.mass,
+                orbit,
+                cull_mode: body.cull,
+                mesh_radius: mesh.radius,
+                warp_key,
+                on_spaceship: false,
+                frame: Mat4::identity(),
+                world_matrix: Mat4::identity(),
+                world_position: position,
+            });
+        }
+
+        // Un cuerpo no puede ser su propio ancestro
+        for (index, (entry, _)) in self.description.entries().enumerate() {
+            let mut ancestor = models[index].parent;
+            for _ in 0..models.len() {
+                match ancestor {
+                    Some(parent) if parent == index => {
+                        return Err(invalid(&entry, "parent chain forms a cycle".to_string()));
+                    }
+                    Some(parent) => ancestor = models[parent].parent,
+                    None => break,
+                }
+            }
+        }
+
+        // Lo que cuelga de la nave (directamente o a través de otros cuerpos) viaja con ella
+        let spaceship = models.len() - 1;
+        for index in 0..spaceship {
+            let mut ancestor = models[index].parent;
+            while let Some(parent) = ancestor {
+                if parent == spaceship {
+                    models[index].on_spaceship = true;
+                    break;
+                }
+                ancestor = models[parent].parent;
+            }
+        }
+
+        Ok(models)
+    }
+
+    // Luces de la escena; las que siguen a un cuerpo guardan el índice de su modelo
+    pub fn lights(&self) -> Result<Vec<SceneLight>, SceneError> {
+        let mut lights = Vec::with_capacity(self.description.lights.len());
+
+        for (index, light) in self.description.lights.iter().enumerate() {
+            let entry = format!("lights[{}]", index);
+            let scene_light = match light {
+                LightDescription::Point { position, body, color, intensity } => {
+                    let color = Color::new(color[0], color[1], color[2]);
+                    match (position, body) {
+                        (Some(position), None) => SceneLight {
+                            light: Light::point(vec3(*position), color, *intensity),
+                            body: None,
+                        },
+                        (None, Some(name)) => {
+                            let body = self.description.body_index(name)
+                                .ok_or_else(|| invalid(&entry, format!("unknown body \"{}\"", name)))?;
+                            SceneLight {
+                                light: Light::point(Vec3::zeros(), color, *intensity),
+                                body: Some(body),
+                            }
+                        }
+                        _ => return Err(invalid(&entry, "a point light needs either position or body".to_string())),
+                    }
+                }
+                LightDescription::Directional { direction, color, intensity } => {
+                    let direction = vec3(*direction);
+                    if direction.magnitude() == 0.0 {
+                        return Err(invalid(&entry, "direction must not be zero".to_string()));
+                    }
+                    SceneLight {
+                        light: Light::directional(direction, Color::new(color[0], color[1], color[2]), *intensity),
+                        body: None,
+                    }
+                }
+            };
+            lights.push(scene_light);
+        }
+
+        Ok(lights)
+    }
+}
+
+impl SceneDescription {
+    // Índice del modelo del cuerpo llamado `name` (los modelos siguen el orden de `entries`)
+    fn body_index(&self, name: &str) -> Option<usize> {
+        self.entries().position(|(_, body)| body.name == name)
+    }
+
+    // Nombre del cuerpo del modelo `index`; la nave va después de todos los cuerpos
+    pub fn body_name(&self, index: usize) -> &str {
+        self.bodies.get(index).unwrap_or(&self.spaceship).name.as_str()
+    }
+
+    // Todos los cuerpos con el nombre de su entrada en el archivo, la nave al final
+    fn entries(&self) -> impl Iterator<Item = (String, &BodyDescription)> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .map(|(index, body)| (format!("bodies[{}] (\"{}\")", index, body.name), body))
+            .chain(std::iter::once(("spaceship".to_string(), &self.spaceship)))
+    }
+}
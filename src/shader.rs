@@ -3,23 +3,37 @@ use crate::vertex::Vertex;
 use crate::Uniforms;
 use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::light::{diffuse, illuminate};
+use crate::noise_cache::CurrentsNoise;
 use rand::Rng;
 use std::f32::consts::PI;
 use fastnoise_lite::{FastNoiseLite, NoiseType, FractalType};
 
+// Escala entre el espacio de objeto (las esferas tienen radio ~0.5) y el espacio de ruido. Con ella,
+// las frecuencias de ruido que se ajustaron para coordenadas de pantalla dan un tamaño de detalle
+// parecido al de un planeta visto de cerca.
+pub const OBJECT_NOISE_SCALE: f32 = 100.0;
+
+// Los factores de tiempo de los shaders se ajustaron cuando `Uniforms.time` contaba frames (a unos
+// 30 por segundo); ahora son segundos simulados, así que cada factor original se multiplica por esta
+// constante para que las animaciones conserven su velocidad.
+pub const BASELINE_FRAMES_PER_SECOND: f32 = 30.0;
+
+// Posición del fragmento en espacio de objeto escalada para muestrear ruido 3D. Como depende solo de
+// la superficie del modelo, los patrones rotan con el cuerpo y no cambian con la cámara.
+pub fn object_noise_position(fragment: &Fragment) -> Vec3 {
+    fragment.vertex_position * OBJECT_NOISE_SCALE
+}
+
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
-    // Aplicar la matriz de transformación completa (precomputada)
-    let transformed = uniforms.transformation_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
-    
-    let w = transformed.w;
-    let ndc_position = Vec4::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w,
-        1.0
-    );
+    // Aplicar la matriz de transformación completa (precomputada) hasta espacio de recorte.
+    // La división por w se hace después del recorte, en `viewport_transform`.
+    let clip_position = uniforms.transformation_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
 
-    let transformed_position = uniforms.viewport_matrix * ndc_position;
+    // Llevar la normal a espacio de mundo con la matriz normal (inversa transpuesta de la matriz de
+    // modelo), para que la iluminación siga la rotación del modelo
+    let transformed_normal = (uniforms.normal_matrix * vertex.normal).normalize();
+    let world_position = uniforms.model_matrix * Vec4::new(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
 
     // Crear un nuevo vértice con los atributos transformados
     Vertex {
@@ -27,11 +41,30 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         normal: vertex.normal,
         tex_coords: vertex.tex_coords,
         color: vertex.color,
-        transformed_position: Vec3::new(transformed_position.x, transformed_position.y, transformed_position.z),
-        transformed_normal: vertex.normal,
+        transformed_position: Vec3::new(0.0, 0.0, 0.0),
+        transformed_normal,
+        clip_position,
+        world_position: Vec3::new(world_position.x, world_position.y, world_position.z),
+        inv_w: 1.0,
     }
 }
 
+// División de perspectiva y transformación a coordenadas de pantalla.
+// Solo debe llamarse con vértices ya recortados (w > 0).
+pub fn viewport_transform(vertex: &mut Vertex, uniforms: &Uniforms) {
+    let w = vertex.clip_position.w;
+    let ndc_position = Vec4::new(
+        vertex.clip_position.x / w,
+        vertex.clip_position.y / w,
+        vertex.clip_position.z / w,
+        1.0
+    );
+
+    let screen_position = uniforms.viewport_matrix * ndc_position;
+    vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+    vertex.inv_w = 1.0 / w;
+}
+
 // Retorna un Color en lugar de un Fragment
 pub fn fragment_shader(_fragment: &Fragment) -> Color {
     Color::new(255, 0, 0)
@@ -43,16 +76,12 @@ pub fn depth_based_fragment_shader(fragment: &Fragment, base_color: Color) -> Co
     let brightness_factor: f32 = 1.0 - (fragment.depth * 0.5);
     let brightness_factor = brightness_factor.clamp(0.0, 1.0);
 
-    // Aplicar el ajuste de brillo al color base
-    let mut adjusted_color = Color {
+    // Aplicar el ajuste de brillo al color base (la iluminación ya la aplicó cada shader)
+    Color {
         r: (base_color.r as f32 * brightness_factor) as u8,
         g: (base_color.g as f32 * brightness_factor) as u8,
         b: (base_color.b as f32 * brightness_factor) as u8,
-    };
-
-    // Multiplicar el color ajustado por la intensidad de la luz
-    adjusted_color = adjusted_color * fragment.intensity;
-    adjusted_color
+    }
 }
 
 // Modificado para retornar un Color
@@ -76,7 +105,7 @@ pub fn pattern_fragment_shader(fragment: &Fragment) -> Color {
 
 pub fn time_based_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Usar el tiempo para controlar el aumento progresivo de la intensidad del color rojo
-    let red_intensity = (uniforms.time as f32 * 0.05).sin();  // Cambia el rojo con el tiempo
+    let red_intensity = (uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.05).sin();  // Cambia el rojo con el tiempo
     let dynamic_red_intensity = (red_intensity + 1.0) / 2.0;  // Convertir de -1 a 1 en 0 a 1 (rango entre 0 y 1)
 
     // Mantener una mezcla entre el color original y el rojo creciente
@@ -108,7 +137,7 @@ pub fn simple_pattern_shader(fragment: &Fragment) -> Color {
 
 
 pub fn moving_pattern_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let time_factor = (uniforms.time as f32 * 0.1).sin(); // Factor de tiempo basado en seno
+    let time_factor = (uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.1).sin(); // Factor de tiempo basado en seno
 
     let stripe_width = 20.0; // Define el ancho de la franja
     let movement_offset = time_factor * 50.0; // Movimiento basado en el tiempo
@@ -179,7 +208,7 @@ pub fn overlay_shader(fragment: &Fragment, blend_color: Color) -> Color {
 
 pub fn time_movement_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Cambiar el color con el tiempo para crear movimiento
-    let time_factor = (uniforms.time as f32 * 0.1).sin();
+    let time_factor = (uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.1).sin();
     let blend_color = Color::new(100, (time_factor * 255.0) as u8, 150);
 
     // Usar el modo overlay para crear contraste dinámico
@@ -194,8 +223,8 @@ pub fn time_movement_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
 pub fn exceptional_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Oscilaciones para crear distorsión
-    let wave = (uniforms.time as f32 * 0.05).sin() + (fragment.position.y * 0.1).sin();
-    let time_factor = (uniforms.time as f32 * 0.05).cos();
+    let wave = (uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.05).sin() + (fragment.position.y * 0.1).sin();
+    let time_factor = (uniforms.time * BASELINE_FRAMES_PER_SECOND * 0.05).cos();
 
     // Mezcla de colores base, dinámico con el tiempo
     let base_color1 = Color::new(255, 0, 128);  // Un magenta vibrante
@@ -215,8 +244,9 @@ pub fn exceptional_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) ->
     // Efecto de fusión: Usamos el *blend multiply* para dar un aspecto distorsionado
     let final_color = moving_color.blend_multiply(&bright_color);
 
-    // Aplicar la intensidad para un efecto de vibración
-    let vibrated_color = final_color * (fragment.intensity * wave.abs());
+    // Aplicar la luz difusa de la escena con un efecto de vibración
+    let light = diffuse(uniforms.lights, &fragment.normal, &fragment.world_position).min(1.0);
+    let vibrated_color = final_color * (light * wave.abs());
 
     // Retornar el color vibrado
     vibrated_color
@@ -264,7 +294,7 @@ pub fn noise_based_fragment_shader(fragment: &Fragment, uniforms: &Uniforms) ->
 // Shader para crear nubes en movimiento sobre una superficie de planeta, devolviendo un Color
 pub fn moving_clouds_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     // Usar tiempo para animar las nubes en el eje x
-    let time_factor = uniforms.time as f32 * 1.1; // Ajusta la velocidad de movimiento de las nubes
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 1.1; // Ajusta la velocidad de movimiento de las nubes
 
     // Obtener el valor de ruido para la posición actual del fragmento (x, y), desplazado por el tiempo para simular movimiento
     let noise_value = uniforms.noise.get_noise_2d(fragment.position.x + time_factor, fragment.position.y);
@@ -342,34 +372,28 @@ pub fn cracked_earth_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     }
 }
 
-pub fn ocean_currents_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+pub fn ocean_currents_shader(fragment: &Fragment, uniforms: &Uniforms, noises: &CurrentsNoise) -> Color {
     // Colores base para el agua
     let deep_ocean_color = Color::new(0, 70, 135);       // Azul profundo
     let current_color = Color::new(64, 162, 216);        // Azul claro para corrientes
     let warm_ocean_color = Color::new(0, 140, 150);      // Verde azulado para zonas cálidas
 
     // Configurar ruido para las corrientes de agua
-    let mut current_noise = FastNoiseLite::new();
-    current_noise.set_noise_type(Some(NoiseType::Perlin));
-    current_noise.set_frequency(Some(0.08));
-
-    let mut wave_noise = FastNoiseLite::new();
-    wave_noise.set_noise_type(Some(NoiseType::OpenSimplex2));
-    wave_noise.set_frequency(Some(0.1));                 // Variaciones de flujo
+    let current_noise = &noises.current;
+    let wave_noise = &noises.wave;     // Variaciones de flujo
 
     // Movimiento y dirección de las corrientes
-    let time_factor = uniforms.time as f32 * 1.5;
-    let current_value = current_noise.get_noise_2d(fragment.position.x + time_factor, fragment.position.y);
-    let wave_value = wave_noise.get_noise_2d(fragment.position.x, fragment.position.y + time_factor);
+    let time_factor = uniforms.time * BASELINE_FRAMES_PER_SECOND * 1.5;
+    let p = object_noise_position(fragment);
+    let current_value = current_noise.get_noise_3d(p.x + time_factor, p.y, p.z);
+    let wave_value = wave_noise.get_noise_3d(p.x, p.y + time_factor, p.z);
 
     // Color base del océano
     let ocean_base = deep_ocean_color.lerp(&current_color, current_value.abs() * 0.6);
     let ocean_with_temp = ocean_base.lerp(&warm_ocean_color, wave_value * 0.3); // Zonas cálidas en el océano
 
     // Reflejos suaves y destellos de luz
-    let light_dir = Vec3::new(1.0, 1.0, -1.0).normalize();
-    let intensity = fragment.normal.dot(&light_dir).max(0.0);
-    let highlighted_color = ocean_with_temp * (0.7 + 0.3 * intensity);
+    let highlighted_color = illuminate(ocean_with_temp, 0.7, 0.3, uniforms.lights, &fragment.normal, &fragment.world_position);
 
     // Retornar el color con reflejo de luz y corrientes en movimiento
     depth_based_fragment_shader(fragment, highlighted_color)
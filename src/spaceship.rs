@@ -0,0 +1,177 @@
+// spaceship.rs
+// Modelo de vuelo de la nave: tiene posición, velocidad y orientación propias y se mueve con las
+// leyes de Newton. Los controles dan empuje sobre los tres ejes de la nave y giro en cabeceo,
+// guiñada y alabeo (6 grados de libertad). No hay rozamiento: la nave sigue a la misma velocidad
+// hasta que se frena. La gravedad de los cuerpos con masa se puede activar o desactivar.
+use nalgebra_glm::{Mat3, Quat, Vec3, mat3_to_quat, quat_angle_axis, quat_normalize, quat_rotate_vec3};
+use crate::camera::Camera;
+use crate::collision::{move_and_collide, BodySweep, CollisionEvent};
+use crate::nbody::GRAVITATIONAL_CONSTANT;
+use crate::Model;
+
+// Aceleración de los propulsores en unidades por segundo al cuadrado
+const THRUST: f32 = 12.0;
+
+// Velocidad de giro en radianes por segundo
+const TURN_SPEED: f32 = 1.5;
+
+// Suavizado de la gravedad para que no se dispare al pasar por el centro de un cuerpo
+const GRAVITY_SOFTENING: f32 = 1.0;
+
+// Distancia a la que se coloca la nave frente a la cámara al empezar o al saltar a un planeta
+const CAMERA_DISTANCE: f32 = 2.5;
+
+// Posición de la cámara de persecución respecto a la nave (detrás y un poco arriba)
+const CHASE_DISTANCE: f32 = 2.5;
+const CHASE_HEIGHT: f32 = 0.6;
+
+// Qué tan rápido alcanza la cámara de persecución su posición (mayor es más rígida)
+const CHASE_STIFFNESS: f32 = 8.0;
+
+// Ejes de la malla de la nave: la punta mira hacia +X y el techo hacia +Y
+const LOCAL_FORWARD: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+const LOCAL_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+const LOCAL_RIGHT: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+pub struct Spaceship {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub orientation: Quat,
+    pub throttle: Vec3, // Empuje pedido en ejes de la nave: x adelante, y arriba, z derecha (-1 a 1)
+    pub turn: Vec3,     // Giro pedido: x cabeceo, y guiñada, z alabeo (-1 a 1)
+    pub braking: bool,  // Los propulsores empujan contra la velocidad hasta detener la nave
+    pub gravity: bool,
+    pub chase_camera: bool,
+}
+
+impl Spaceship {
+    // Nave detenida frente a la cámara, mirando en la misma dirección
+    pub fn in_front_of(camera: &Camera) -> Self {
+        let mut spaceship = Spaceship {
+            position: Vec3::zeros(),
+            velocity: Vec3::zeros(),
+            orientation: Quat::identity(),
+            throttle: Vec3::zeros(),
+            turn: Vec3::zeros(),
+            braking: false,
+            gravity: false,
+            chase_camera: true,
+        };
+        spaceship.place_in_front_of(camera);
+        spaceship
+    }
+
+    // Mueve la nave frente a la cámara y la detiene (por ejemplo, después de saltar a un planeta)
+    pub fn place_in_front_of(&mut self, camera: &Camera) {
+        let forward = camera.get_forward_vector();
+        let right = forward.cross(&camera.up).normalize();
+        let up = right.cross(&forward);
+
+        // Las columnas son los ejes locales de la nave (adelante, arriba, derecha) en espacio de mundo
+        self.orientation = mat3_to_quat(&Mat3::from_columns(&[forward, up, right]));
+        self.position = camera.eye + forward * CAMERA_DISTANCE;
+        self.velocity = Vec3::zeros();
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, &LOCAL_FORWARD)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, &LOCAL_UP)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        quat_rotate_vec3(&self.orientation, &LOCAL_RIGHT)
+    }
+
+    // Gira la nave y actualiza su velocidad durante `dt` segundos. `bodies` son los cuerpos que la atraen
+    pub fn accelerate(&mut self, dt: f32, bodies: &[Model]) {
+        // Girar sobre los ejes locales de la nave
+        let turn = self.turn * (TURN_SPEED * dt);
+        self.orientation = quat_normalize(
+            &(self.orientation
+                * quat_angle_axis(turn.x, &LOCAL_RIGHT)
+                * quat_angle_axis(turn.y, &LOCAL_UP)
+                * quat_angle_axis(turn.z, &LOCAL_FORWARD)),
+        );
+
+        let mut acceleration = if self.braking {
+            // Frenar sin pasarse: como mucho la aceleración que deja la velocidad en cero este paso
+            let speed = self.velocity.magnitude();
+            if speed > 0.0 {
+                -self.velocity / speed * THRUST.min(speed / dt)
+            } else {
+                Vec3::zeros()
+            }
+        } else {
+            let throttle = self.throttle;
+            (self.forward() * throttle.x + self.up() * throttle.y + self.right() * throttle.z) * THRUST
+        };
+
+        if self.gravity {
+            // Lo que lleva la nave consigo no la atrae
+            for body in bodies.iter().filter(|body| body.mass > 0.0 && !body.on_spaceship) {
+                let offset = body.world_position - self.position;
+                let distance_squared = offset.magnitude_squared() + GRAVITY_SOFTENING * GRAVITY_SOFTENING;
+                acceleration += offset * (GRAVITATIONAL_CONSTANT * body.mass / (distance_squared * distance_squared.sqrt()));
+            }
+        }
+
+        self.velocity += acceleration * dt;
+    }
+
+    // Mueve la nave con su velocidad durante `dt` segundos (Euler semi-implícito, después de
+    // `accelerate`), chocando contra los cuerpos. Devuelve los choques del paso.
+    pub fn fly(&mut self, dt: f32, radius: f32, bodies: &[BodySweep]) -> Vec<CollisionEvent> {
+        let (position, events) = move_and_collide(self.position, &mut self.velocity, radius, dt, bodies);
+        self.position = position;
+        events
+    }
+
+    // Copia la posición y orientación de la nave en su modelo
+    pub fn sync_model(&self, model: &mut Model) {
+        model.position = self.position;
+        model.rotation = self.orientation;
+    }
+
+    // Lleva la cámara detrás de la nave, suavizando el movimiento con el tiempo real del frame
+    pub fn chase(&self, camera: &mut Camera, frame_dt: f32) {
+        let forward = self.forward();
+        let up = self.up();
+        let target_eye = self.position - forward * CHASE_DISTANCE + up * CHASE_HEIGHT;
+        let blend = 1.0 - (-CHASE_STIFFNESS * frame_dt).exp();
+
+        camera.eye += (target_eye - camera.eye) * blend;
+        camera.center = self.position + forward * CHASE_DISTANCE;
+        camera.up = up;
+        camera.has_changed = true;
+    }
+
+    pub fn toggle_chase_camera(&mut self, camera: &mut Camera) {
+        self.chase_camera = !self.chase_camera;
+        if self.chase_camera {
+            // La cámara de persecución no puede seguir bloqueada en otro cuerpo ni volar libre
+            camera.release();
+            camera.leave_free_fly();
+        } else {
+            // La cámara orbital usa el "arriba" original de la escena
+            camera.up = camera.default_up;
+            camera.has_changed = true;
+        }
+    }
+
+    pub fn toggle_gravity(&mut self) {
+        self.gravity = !self.gravity;
+    }
+}
+
+impl std::fmt::Display for Spaceship {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "nave {:.1} u/s", self.velocity.magnitude())?;
+        if self.gravity {
+            write!(f, ", gravedad")?;
+        }
+        Ok(())
+    }
+}
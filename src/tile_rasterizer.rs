@@ -0,0 +1,157 @@
+// tile_rasterizer.rs
+// Rasterizador por tiles: los triángulos en pantalla se agrupan por tile y cada tile se rasteriza
+// y sombrea en paralelo, escribiendo directamente en el framebuffer.
+use std::sync::Mutex;
+use std::thread;
+use crate::color::Color;
+use crate::fragment::{Fragment, FragmentVisitor};
+use crate::framebuffer::{Framebuffer, FramebufferTile};
+use crate::triangle::{calculate_bounding_box, triangle, InterpolationMode};
+use crate::vertex::Vertex;
+
+// Tamaño del lado de cada tile en pixeles
+pub const TILE_SIZE: usize = 32;
+
+// Triángulo ya recortado y en coordenadas de pantalla, junto con el índice de la llamada de
+// dibujo (modelo) a la que pertenece para poder elegir su shader y uniforms
+pub struct ScreenTriangle {
+    pub vertices: [Vertex; 3],
+    pub draw: usize,
+}
+
+// Listas de triángulos que tocan cada tile, en el orden en que fueron enviados
+struct TileBins {
+    tiles_x: usize,
+    tiles_y: usize,
+    bins: Vec<Vec<usize>>,
+}
+
+impl TileBins {
+    fn new(width: usize, height: usize) -> Self {
+        let tiles_x = width.div_ceil(TILE_SIZE);
+        let tiles_y = height.div_ceil(TILE_SIZE);
+        TileBins {
+            tiles_x,
+            tiles_y,
+            bins: vec![Vec::new(); tiles_x * tiles_y],
+        }
+    }
+
+    // Agrega el triángulo a todos los tiles que cubre su caja envolvente
+    fn bin(&mut self, index: usize, tri: &ScreenTriangle) {
+        let [a, b, c] = [
+            tri.vertices[0].transformed_position,
+            tri.vertices[1].transformed_position,
+            tri.vertices[2].transformed_position,
+        ];
+        let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+        if max_x < 0 || max_y < 0 {
+            return;
+        }
+
+        let first_x = min_x.max(0) as usize / TILE_SIZE;
+        let first_y = min_y.max(0) as usize / TILE_SIZE;
+        let last_x = (max_x as usize / TILE_SIZE).min(self.tiles_x - 1);
+        let last_y = (max_y as usize / TILE_SIZE).min(self.tiles_y - 1);
+
+        for tile_y in first_y..=last_y {
+            for tile_x in first_x..=last_x {
+                self.bins[tile_y * self.tiles_x + tile_x].push(index);
+            }
+        }
+    }
+
+    fn tile(&self, tile_x: usize, tile_y: usize) -> &[usize] {
+        &self.bins[tile_y * self.tiles_x + tile_x]
+    }
+}
+
+// Rasteriza y sombrea todos los triángulos del frame. `shade` recibe el índice de la llamada de
+// dibujo y el fragmento, y devuelve el color y la emisión opcional del pixel.
+pub fn rasterize_tiles<S>(
+    framebuffer: &mut Framebuffer,
+    triangles: &[ScreenTriangle],
+    mode: InterpolationMode,
+    shade: &S,
+) where
+    S: Fn(usize, &Fragment) -> (Color, Option<Color>) + Sync,
+{
+    let mut bins = TileBins::new(framebuffer.width, framebuffer.height);
+    for (index, tri) in triangles.iter().enumerate() {
+        bins.bin(index, tri);
+    }
+
+    // Los tiles no se solapan, así que los hilos nunca escriben en los mismos pixeles. Los hilos
+    // toman tiles de una cola compartida hasta vaciarla; como cada tile es pequeño, un planeta que
+    // llena unas pocas filas de la pantalla igual se reparte entre todos los hilos.
+    let tiles = framebuffer.tiles_mut(TILE_SIZE);
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(tiles.len());
+    let queue = Mutex::new(tiles);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let tile = match queue.lock().unwrap().pop() {
+                    Some(tile) => tile,
+                    None => break,
+                };
+                rasterize_tile(tile, &bins, triangles, mode, shade);
+            });
+        }
+    });
+}
+
+fn rasterize_tile<S>(
+    mut tile: FramebufferTile,
+    bins: &TileBins,
+    triangles: &[ScreenTriangle],
+    mode: InterpolationMode,
+    shade: &S,
+) where
+    S: Fn(usize, &Fragment) -> (Color, Option<Color>) + Sync,
+{
+    let bounds = (
+        tile.x0 as i32,
+        tile.y0 as i32,
+        (tile.x0 + tile.columns) as i32 - 1,
+        (tile.y0 + tile.rows()) as i32 - 1,
+    );
+
+    for &index in bins.tile(tile.x0 / TILE_SIZE, tile.y0 / TILE_SIZE) {
+        let tri = &triangles[index];
+        let [v1, v2, v3] = &tri.vertices;
+
+        let mut visitor = TileShader { tile: &mut tile, draw: tri.draw, shade };
+        triangle(v1, v2, v3, mode, bounds, &mut visitor);
+    }
+}
+
+// Visitante que hace early-Z contra el tile y sombrea solo los fragmentos visibles
+struct TileShader<'a, 'b, S> {
+    tile: &'a mut FramebufferTile<'b>,
+    draw: usize,
+    shade: &'a S,
+}
+
+impl<'a, 'b, S> FragmentVisitor for TileShader<'a, 'b, S>
+where
+    S: Fn(usize, &Fragment) -> (Color, Option<Color>),
+{
+    fn test_depth(&mut self, x: usize, y: usize, depth: f32) -> bool {
+        self.tile.depth_test(x, y, depth)
+    }
+
+    fn visit(&mut self, fragment: Fragment) {
+        let (color, emission) = (self.shade)(self.draw, &fragment);
+        self.tile.point(
+            fragment.position.x as usize,
+            fragment.position.y as usize,
+            fragment.depth,
+            color.to_hex(),
+            emission.map_or(0, |emission| emission.to_hex()),
+        );
+    }
+}
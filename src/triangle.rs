@@ -1,28 +1,53 @@
-use nalgebra_glm::{Vec3, dot, Vec2};
-use crate::fragment::Fragment;
+use nalgebra_glm::{Vec3, Vec2};
+use crate::fragment::{Fragment, FragmentVisitor};
 use crate::vertex::Vertex;
 use crate::line::line;
 use crate::color::Color;
 
-pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
-
+pub fn _triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex, visitor: &mut impl FragmentVisitor) {
     // Dibujar los tres lados del triángulo
-    fragments.extend(line(v1, v2));
-    fragments.extend(line(v2, v3));
-    fragments.extend(line(v3, v1));
+    line(v1, v2, visitor);
+    line(v2, v3, visitor);
+    line(v3, v1, visitor);
+}
 
-    fragments
+// Modo de interpolación de atributos dentro del triángulo
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterpolationMode {
+    PerspectiveCorrect, // Interpola atributos/w y divide por 1/w interpolado
+    Affine,             // Interpolación lineal en espacio de pantalla (modo anterior)
 }
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+impl InterpolationMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            InterpolationMode::PerspectiveCorrect => InterpolationMode::Affine,
+            InterpolationMode::Affine => InterpolationMode::PerspectiveCorrect,
+        }
+    }
+}
+
+// Rasteriza el triángulo dentro del rectángulo `bounds` (min_x, min_y, max_x, max_y, inclusivo)
+// y entrega cada fragmento a `visitor` sin acumularlos en memoria. La prueba de profundidad del
+// visitante se hace antes de interpolar el resto de atributos.
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    mode: InterpolationMode,
+    bounds: (i32, i32, i32, i32),
+    visitor: &mut impl FragmentVisitor,
+) {
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    let (min_x, min_y) = (min_x.max(bounds.0), min_y.max(bounds.1));
+    let (max_x, max_y) = (max_x.min(bounds.2), max_y.min(bounds.3));
 
-    let light_dir = Vec3::new(0.0, 0.0, 2.0);  // Dirección de la luz
     let triangle_area = edge_function(&a, &b, &c);
+    if triangle_area == 0.0 {
+        return;
+    }
 
     for y in min_y..=max_y {
         for x in min_x..=max_x {
@@ -30,31 +55,47 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
             let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
             if w1 >= 0.0 && w1 <= 1.0 && w2 >= 0.0 && w2 <= 1.0 && w3 >= 0.0 && w3 <= 1.0 {
-                let normal = (v1.transformed_normal * w1 + v2.transformed_normal * w2 + v3.transformed_normal * w3).normalize();
-                let intensity = dot(&normal, &light_dir).max(0.0);
-
-                // Interpolación de la posición del vértice y coordenadas UV
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
-                let tex_coords = v1.tex_coords * w1 + v2.tex_coords * w2 + v3.tex_coords * w3;
+                // La profundidad es lineal en pantalla, así que usa los baricéntricos sin corregir
                 let depth = a.z * w1 + b.z * w2 + c.z * w3;
+                if !visitor.test_depth(x as usize, y as usize, depth) {
+                    continue;
+                }
+
+                let inv_w = v1.inv_w * w1 + v2.inv_w * w2 + v3.inv_w * w3;
+
+                // Pesos para los atributos: corregidos por perspectiva o afines
+                let (p1, p2, p3) = match mode {
+                    InterpolationMode::PerspectiveCorrect => (
+                        w1 * v1.inv_w / inv_w,
+                        w2 * v2.inv_w / inv_w,
+                        w3 * v3.inv_w / inv_w,
+                    ),
+                    InterpolationMode::Affine => (w1, w2, w3),
+                };
 
-                fragments.push(Fragment::new(
-                    Vec2::new(x as f32, y as f32),   // position
-                    Color::new(255, 255, 255),       // color
-                    depth,                           // depth
-                    normal,                          // normal
-                    intensity,                       // intensity
-                    vertex_position,                 // vertex_position (Vec3)
-                    tex_coords,                      // tex_coords
-                ));
+                // La iluminación la calcula cada shader con las luces de la escena (`Uniforms.lights`)
+                let normal = (v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3).normalize();
+
+                // Interpolación de la posición del vértice y coordenadas UV
+                let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+                let tex_coords = v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3;
+                let world_position = v1.world_position * p1 + v2.world_position * p2 + v3.world_position * p3;
+
+                visitor.visit(Fragment {
+                    position: Vec2::new(x as f32, y as f32),
+                    color: Color::new(255, 255, 255),
+                    depth,
+                    normal,
+                    vertex_position,
+                    tex_coords,
+                    world_position,
+                });
             }
         }
     }
-
-    fragments
 }
 
-fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
+pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
     let max_x = v1.x.max(v2.x).max(v3.x).ceil() as i32;
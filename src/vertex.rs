@@ -1,4 +1,4 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{Vec2, Vec3, Vec4};
 use crate::color::Color;
 
 #[derive(Clone, Debug)]
@@ -9,6 +9,9 @@ pub struct Vertex {
   pub color: Color,
   pub transformed_position: Vec3,
   pub transformed_normal: Vec3,
+  pub clip_position: Vec4, // Posición en espacio de recorte (antes de dividir por w)
+  pub world_position: Vec3, // Posición en espacio de mundo (matriz de modelo aplicada)
+  pub inv_w: f32,           // 1/w tras la división de perspectiva, para interpolar con corrección de perspectiva
 }
 
 impl Vertex {
@@ -20,6 +23,9 @@ impl Vertex {
       color: Color::black(),
       transformed_position: position,
       transformed_normal: normal,
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      world_position: position,
+      inv_w: 1.0,
     }
   }
 
@@ -31,6 +37,9 @@ impl Vertex {
       color,
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+      clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+      world_position: position,
+      inv_w: 1.0,
     }
   }
 
@@ -38,6 +47,21 @@ impl Vertex {
     self.transformed_position = position;
     self.transformed_normal = normal;
   }
+
+  // Interpola todos los atributos entre dos vértices (usado al recortar en espacio de recorte)
+  pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+    Vertex {
+      position: self.position + (other.position - self.position) * t,
+      normal: self.normal + (other.normal - self.normal) * t,
+      tex_coords: self.tex_coords + (other.tex_coords - self.tex_coords) * t,
+      color: self.color.lerp(&other.color, t),
+      transformed_position: self.transformed_position + (other.transformed_position - self.transformed_position) * t,
+      transformed_normal: self.transformed_normal + (other.transformed_normal - self.transformed_normal) * t,
+      clip_position: self.clip_position + (other.clip_position - self.clip_position) * t,
+      world_position: self.world_position + (other.world_position - self.world_position) * t,
+      inv_w: self.inv_w + (other.inv_w - self.inv_w) * t,
+    }
+  }
 }
 
 impl Default for Vertex {
@@ -49,6 +73,9 @@ impl Default for Vertex {
       color: Color::black(),
       transformed_position: Vec3::new(0.0, 0.0, 0.0),
       transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+      clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+      world_position: Vec3::new(0.0, 0.0, 0.0),
+      inv_w: 1.0,
     }
   }
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+// warp.rs
+// Saltos animados a un cuerpo de la escena. En lugar de teletransportar la cámara, ésta vuela
+// desde donde está hasta quedar a `distance` del cuerpo, con una curva que acelera al inicio y
+// frena al final, mirando al cuerpo (el centro de la cámara queda en él, así que al llegar la
+// cámara orbital gira a su alrededor) mientras su "arriba" vuelve poco a poco al de la escena.
+// El punto de llegada se recalcula cada frame, así que el salto sigue al cuerpo aunque se mueva en
+// su órbita durante el vuelo.
+use nalgebra_glm::{Mat3, Quat, Vec3, mat3_to_quat, quat_rotate_vec3, quat_slerp};
+use crate::camera::Camera;
+use crate::Model;
+
+// Eje "arriba" local de la orientación de la cámara (la misma columna que usa la nave)
+const LOCAL_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+
+pub struct Warp {
+    pub target: usize, // Índice del modelo al que se salta
+    start_eye: Vec3,
+    start_orientation: Quat,
+    approach_direction: Vec3, // Del cuerpo hacia el punto de llegada (fija durante el vuelo)
+    elapsed: f32,
+    duration: f32,
+    distance: f32,
+}
+
+impl Warp {
+    // Empieza un salto desde la posición actual de la cámara. Se llega por el mismo lado del cuerpo
+    // desde el que se mira.
+    pub fn new(camera: &Camera, models: &[Model], target: usize, duration: f32, distance: f32) -> Self {
+        let offset = camera.eye - models[target].world_position;
+        let approach_direction = if offset.magnitude_squared() > 0.0 {
+            offset.normalize()
+        } else {
+            -camera.get_forward_vector()
+        };
+
+        Warp {
+            target,
+            start_eye: camera.eye,
+            start_orientation: look_orientation(&camera.get_forward_vector(), &camera.up),
+            approach_direction,
+            elapsed: 0.0,
+            duration,
+            distance,
+        }
+    }
+
+    // Fracción del vuelo ya recorrida, de 0 a 1
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).min(1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    // Avanza el vuelo `frame_dt` segundos reales (no depende de la pausa ni de la velocidad del
+    // tiempo) y coloca la cámara. Al terminar la cámara queda mirando al cuerpo con su "arriba" original.
+    pub fn update(&mut self, camera: &mut Camera, models: &[Model], frame_dt: f32) {
+        self.elapsed += frame_dt;
+        let t = self.progress();
+        let eased = t * t * (3.0 - 2.0 * t); // smoothstep: arranca y llega con velocidad cero
+
+        let target_position = models[self.target].world_position;
+        let arrival = target_position + self.approach_direction * self.distance;
+        let eye = self.start_eye + (arrival - self.start_eye) * eased;
+
+        // Mirar al cuerpo desde donde está ahora la cámara; el "arriba" gira poco a poco desde el
+        // que tenía la cámara al salir (por ejemplo de cabeza en vuelo libre)
+        let forward = (target_position - eye).try_normalize(1e-6).unwrap_or(-self.approach_direction);
+        let end_orientation = look_orientation(&forward, &camera.default_up);
+        let orientation = quat_slerp(&self.start_orientation, &end_orientation, eased);
+        let up = quat_rotate_vec3(&orientation, &LOCAL_UP);
+        let up = (up - forward * up.dot(&forward))
+            .try_normalize(1e-6)
+            .unwrap_or_else(|| quat_rotate_vec3(&end_orientation, &LOCAL_UP));
+
+        camera.eye = eye;
+        camera.center = target_position;
+        camera.up = up;
+        camera.pitch = forward.y.atan2((forward.x.powi(2) + forward.z.powi(2)).sqrt());
+        camera.yaw = forward.z.atan2(forward.x);
+        camera.has_changed = true;
+    }
+
+    // Intensidad de las estelas de velocidad: proporcional a la rapidez del vuelo, máxima a la mitad
+    pub fn streak_strength(&self) -> f32 {
+        let t = self.progress();
+        4.0 * t * (1.0 - t)
+    }
+}
+
+// Orientación que mira hacia `forward` con `up` como referencia de "arriba"
+fn look_orientation(forward: &Vec3, up: &Vec3) -> Quat {
+    // Si se mira justo hacia arriba o abajo, cualquier eje perpendicular sirve
+    let right = match forward.cross(up).try_normalize(1e-6) {
+        Some(right) => right,
+        None => forward.cross(&Vec3::new(1.0, 0.0, 0.0)).try_normalize(1e-6).unwrap_or(Vec3::new(0.0, 0.0, 1.0)),
+    };
+    let up = right.cross(forward);
+    mat3_to_quat(&Mat3::from_columns(&[*forward, up, right]))
+}